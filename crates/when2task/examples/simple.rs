@@ -10,13 +10,15 @@ async fn main() {
         tokio::time::sleep(Duration::from_millis(100)).await;
         println!("Task A completed after 100ms");
         Ok::<&str, ()>("A")
-    });
+    })
+    .with_name("A");
 
     let task_b = Task::new_independent(async {
         tokio::time::sleep(Duration::from_millis(150)).await;
         println!("Task B completed after 150ms");
         Ok::<&str, ()>("B")
-    });
+    })
+    .with_name("B");
 
     let task_a_id = *task_a.id();
     let task_b_id = *task_b.id();
@@ -29,7 +31,8 @@ async fn main() {
             Ok::<&str, ()>("C")
         },
         Dependency::from([task_a_id, task_b_id]),
-    );
+    )
+    .with_name("C");
 
     let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
     builder.insert(task_a).insert(task_b).insert(task_c);
@@ -39,6 +42,7 @@ async fn main() {
     let result = executor.execute().await.unwrap();
 
     assert!(start.elapsed().as_millis() < 210);
+    println!("{}", result.summary());
     assert_eq!(2, result.steps.len());
     // Output shows ~200ms total (150ms for step 1 + 50ms for step 2)
     // demonstrating concurrent execution of A & B, then C
@@ -3,12 +3,29 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum BlueprintError {
-    #[error("Circular dependency detected: {0:?}")]
-    CircularDependency(Vec<TaskId>),
+    /// The tasks forming the cycle, each one being a dependency of the next
+    /// and the last one a dependency of the first.
+    #[error("Circular dependency detected: {}", format_cycle(.cycle))]
+    CircularDependency { cycle: Vec<TaskId> },
+
+    #[error("Task {0} depends on itself")]
+    SelfDependency(TaskId),
 
     #[error("Task {0} has missing dependency {1}")]
     MissingDependency(TaskId, TaskId),
 
+    #[error("Task {0} depends on group '{1}', which has no task")]
+    UnknownGroup(TaskId, String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
+
+fn format_cycle(cycle: &[TaskId]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|task_id| task_id.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
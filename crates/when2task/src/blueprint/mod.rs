@@ -1,5 +1,7 @@
 mod errors;
 mod w2t_blueprint;
+mod warnings;
 
 pub use errors::*;
 pub use w2t_blueprint::*;
+pub use warnings::*;
@@ -1,32 +1,320 @@
-use crate::blueprint::BlueprintError;
-use crate::{Task, TaskId};
+use crate::blueprint::{BlueprintError, BlueprintWarning};
+use crate::{Dependency, LocalTask, Task, TaskId};
 use dashmap::DashMap;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Step {
-    pub tasks: Vec<TaskId>,
+    pub(crate) tasks: Vec<TaskId>,
 }
+
+impl Step {
+    /// The tasks of the step, sorted by id unless planned with
+    /// [`Blueprint::from_tasks_ordered`]
+    pub fn tasks(&self) -> &[TaskId] {
+        &self.tasks
+    }
+
+    pub fn contains(&self, id: &TaskId) -> bool {
+        self.tasks.contains(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+/// Measurements of a blueprint construction, see [`Blueprint::from_tasks_timed`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlueprintStats {
+    /// Time spent checking the dependencies, in nanoseconds
+    pub validation_ns: u128,
+    /// Time spent sorting the tasks into steps, in nanoseconds
+    pub sort_ns: u128,
+    pub node_count: usize,
+    /// Number of dependencies the plan orders tasks by
+    pub edge_count: usize,
+}
+
+/// What the planner needs to know about a task
+pub(crate) trait PlanNode {
+    fn dependencies(&self) -> &Dependency;
+    fn name(&self) -> Option<&String>;
+
+    /// Whether the task has to be alone in its step
+    fn exclusive(&self) -> bool {
+        false
+    }
+
+    /// Expected running time of the task
+    fn cost(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Group the task belongs to, see [`Dependency::Group`]
+    fn group(&self) -> Option<&String> {
+        None
+    }
+}
+
+impl<T, E> PlanNode for Task<'_, T, E> {
+    fn dependencies(&self) -> &Dependency {
+        self.dependencies()
+    }
+
+    fn name(&self) -> Option<&String> {
+        self.name().as_ref()
+    }
+
+    fn exclusive(&self) -> bool {
+        *self.exclusive()
+    }
+
+    fn cost(&self) -> Option<Duration> {
+        *self.cost()
+    }
+
+    fn group(&self) -> Option<&String> {
+        self.group().as_ref()
+    }
+}
+
+impl PlanNode for Dependency {
+    fn dependencies(&self) -> &Dependency {
+        self
+    }
+
+    fn name(&self) -> Option<&String> {
+        None
+    }
+}
+
+/// Copy of what the planner needs to know about a task, see [`Blueprint::from_tasks_partial`]
+struct PlanView {
+    dependencies: Dependency,
+    name: Option<String>,
+    exclusive: bool,
+    cost: Option<Duration>,
+    group: Option<String>,
+}
+
+impl PlanView {
+    fn of<N: PlanNode>(node: &N) -> Self {
+        Self {
+            dependencies: node.dependencies().clone(),
+            name: node.name().cloned(),
+            exclusive: node.exclusive(),
+            cost: node.cost(),
+            group: node.group().cloned(),
+        }
+    }
+}
+
+impl PlanNode for PlanView {
+    fn dependencies(&self) -> &Dependency {
+        &self.dependencies
+    }
+
+    fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+
+    fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    fn cost(&self) -> Option<Duration> {
+        self.cost
+    }
+
+    fn group(&self) -> Option<&String> {
+        self.group.as_ref()
+    }
+}
+
+impl<T, E> PlanNode for LocalTask<T, E> {
+    fn dependencies(&self) -> &Dependency {
+        self.dependencies()
+    }
+
+    fn name(&self) -> Option<&String> {
+        self.name().as_ref()
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct Blueprint {
-    pub steps: Vec<Step>,
+    pub(crate) steps: Vec<Step>,
+    pub(crate) task_to_step: HashMap<TaskId, usize>,
+    /// Maps each task to the tasks that directly depend on it
+    pub(crate) dependents: HashMap<TaskId, Vec<TaskId>>,
+    /// Names of the tasks that have one, used for rendering
+    pub(crate) names: HashMap<TaskId, String>,
+    /// Length of the longest dependency chain leading to each task
+    pub(crate) depths: HashMap<TaskId, usize>,
+    /// Expected running times of the tasks that have one
+    pub(crate) costs: HashMap<TaskId, Duration>,
 }
 
 impl Blueprint {
     pub fn from_tasks<T, E>(tasks: &DashMap<TaskId, Task<T, E>>) -> Result<Self, BlueprintError> {
+        Self::from_nodes(tasks, false)
+    }
+
+    /// Like [`from_tasks`](Self::from_tasks), but dependencies on tasks that are not
+    /// part of `tasks` are dropped, as if already satisfied, instead of failing with
+    /// [`BlueprintError::MissingDependency`]
+    pub fn from_tasks_ignoring_missing<T, E>(
+        tasks: &DashMap<TaskId, Task<T, E>>,
+    ) -> Result<Self, BlueprintError> {
+        Self::from_nodes(tasks, true)
+    }
+
+    /// Like [`from_tasks`](Self::from_tasks), but the tasks of each step are ordered by
+    /// decreasing `order` hint, e.g. their priority, instead of by id only. Ties keep
+    /// the id order.
+    pub fn from_tasks_ordered<T, E>(
+        tasks: &DashMap<TaskId, Task<T, E>>,
+        order: impl Fn(&TaskId) -> i32,
+    ) -> Result<Self, BlueprintError> {
+        Self::from_nodes_ordered(tasks, false, Some(&order), None)
+    }
+
+    /// Plans tasks given only by their ids and dependencies, e.g. to validate a plan
+    /// without building the tasks. The steps are the same as with [`from_tasks`](Self::from_tasks).
+    pub fn from_dependencies(
+        dependencies: &HashMap<TaskId, Dependency>,
+    ) -> Result<Self, BlueprintError> {
+        let nodes: DashMap<TaskId, Dependency> = dependencies
+            .iter()
+            .map(|(task_id, dependency)| (*task_id, dependency.clone()))
+            .collect();
+        Self::from_nodes(&nodes, false)
+    }
+
+    pub(crate) fn from_nodes<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+        ignore_missing: bool,
+    ) -> Result<Self, BlueprintError> {
+        Self::from_nodes_ordered(tasks, ignore_missing, None, None)
+    }
+
+    /// Like [`from_tasks`](Self::from_tasks), also measuring where the construction
+    /// time goes, e.g. to decide whether large blueprints are worth caching
+    pub fn from_tasks_timed<T, E>(
+        tasks: &DashMap<TaskId, Task<T, E>>,
+    ) -> Result<(Self, BlueprintStats), BlueprintError> {
+        let mut stats = BlueprintStats::default();
+        let blueprint = Self::from_nodes_ordered(tasks, false, None, Some(&mut stats))?;
+        Ok((blueprint, stats))
+    }
+
+    /// Like [`from_tasks`](Self::from_tasks), but instead of failing, plans the tasks that
+    /// can be planned and returns every problem with the others, as reported by
+    /// [`TaskExecutorBuilder::validate`](crate::TaskExecutorBuilder::validate). Tasks that
+    /// are part of a cycle, depend on themselves, on a missing task or on an unknown group,
+    /// and the tasks depending on any of those, are left out of the blueprint.
+    pub fn from_tasks_partial<T, E>(
+        tasks: &DashMap<TaskId, Task<T, E>>,
+    ) -> (Self, Vec<BlueprintError>) {
+        let mut errors = Self::validate_nodes(tasks, false);
+        let views: DashMap<TaskId, PlanView> = match Self::resolve_groups(tasks) {
+            Some((views, unknown)) => {
+                for err in unknown {
+                    if let BlueprintError::UnknownGroup(task_id, _) = err {
+                        views.remove(&task_id);
+                    }
+                }
+                views
+            }
+            None => tasks
+                .iter()
+                .map(|v| (*v.key(), PlanView::of(v.value())))
+                .collect(),
+        };
+        let plannable = Self::plannable(&views);
+        views.retain(|task_id, _| plannable.contains(task_id));
+        let blueprint = Self::from_nodes(&views, false).unwrap_or_else(|err| {
+            errors.push(err);
+            Self::default()
+        });
+        (blueprint, errors)
+    }
+
+    /// The tasks whose dependencies all exist and are plannable, which the topological
+    /// sort eventually reaches
+    fn plannable<N: PlanNode>(tasks: &DashMap<TaskId, N>) -> HashSet<TaskId> {
+        let mut pending: HashMap<TaskId, usize> = HashMap::with_capacity(tasks.len());
+        let mut dependents: HashMap<TaskId, Vec<TaskId>> = HashMap::with_capacity(tasks.len());
+        for v in tasks.iter() {
+            let mut count = 0;
+            for dep_id in Self::ordering_dependencies(v.value(), tasks, false) {
+                dependents.entry(dep_id).or_default().push(*v.key());
+                count += 1;
+            }
+            pending.insert(*v.key(), count);
+        }
+
+        let mut ready: Vec<TaskId> = pending
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(task_id, _)| *task_id)
+            .collect();
+        let mut plannable = HashSet::with_capacity(tasks.len());
+        while let Some(task_id) = ready.pop() {
+            plannable.insert(task_id);
+            for dependent_id in dependents.get(&task_id).into_iter().flatten() {
+                if let Some(count) = pending.get_mut(dependent_id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(*dependent_id);
+                    }
+                }
+            }
+        }
+        plannable
+    }
+
+    fn from_nodes_ordered<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+        ignore_missing: bool,
+        order: Option<&dyn Fn(&TaskId) -> i32>,
+        stats: Option<&mut BlueprintStats>,
+    ) -> Result<Self, BlueprintError> {
+        if let Some((views, errors)) = Self::resolve_groups(tasks) {
+            if let Some(err) = errors.into_iter().next() {
+                return Err(err);
+            }
+            return Self::from_nodes_ordered(&views, ignore_missing, order, stats);
+        }
+        let started = Instant::now();
         // Validate that all dependencies exist
         for v in tasks.iter() {
             let task_id = v.key();
             let task = v.value();
+            if Self::ordering_dependencies(task, tasks, ignore_missing)
+                .any(|dep_id| dep_id == *task_id)
+            {
+                return Err(BlueprintError::SelfDependency(*task_id));
+            }
             for dep_id in task.dependencies().into_iter() {
-                if !tasks.contains_key(&dep_id) {
+                if !ignore_missing && !tasks.contains_key(&dep_id) {
                     return Err(BlueprintError::MissingDependency(*task_id, dep_id));
                 }
             }
         }
 
+        let validated = Instant::now();
+
         // Perform topological sorting using Kahn's algorithm
-        let mut in_degree: HashMap<TaskId, usize> = HashMap::new();
-        let mut adjacency_list: HashMap<TaskId, Vec<TaskId>> = HashMap::new();
+        let mut in_degree: HashMap<TaskId, usize> = HashMap::with_capacity(tasks.len());
+        let mut adjacency_list: HashMap<TaskId, Vec<TaskId>> = HashMap::with_capacity(tasks.len());
 
         // Initialize in-degrees for all tasks
         for task_id in tasks.iter() {
@@ -37,7 +325,7 @@ impl Blueprint {
         for v in tasks {
             let task_id = v.key();
             let task = v.value();
-            for dep_id in task.dependencies().into_iter() {
+            for dep_id in Self::ordering_dependencies(task, tasks, ignore_missing) {
                 adjacency_list.entry(dep_id).or_default().push(*task_id);
                 *in_degree.get_mut(task_id).ok_or_else(|| {
                     BlueprintError::InternalError(format!(
@@ -47,40 +335,61 @@ impl Blueprint {
             }
         }
 
+        let edge_count = in_degree.values().sum();
         let mut steps = vec![];
-        let mut processed = HashSet::new();
+        let mut processed = HashSet::with_capacity(tasks.len());
+        let mut depths = HashMap::with_capacity(tasks.len());
 
-        // Process tasks level by level
-        loop {
-            // Find all tasks with no remaining dependencies
-            let ready_tasks: Vec<TaskId> = in_degree
-                .iter()
-                .filter(|(task_id, degree)| **degree == 0 && !processed.contains(*task_id))
-                .map(|(task_id, _)| *task_id)
-                .collect();
+        // Tasks whose dependencies all belong to earlier levels, starting with the roots
+        let mut next_level: Vec<TaskId> = in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(task_id, _)| *task_id)
+            .collect();
 
+        // Process tasks level by level
+        for depth in 0.. {
+            let mut ready_tasks = std::mem::take(&mut next_level);
             if ready_tasks.is_empty() {
                 break;
             }
 
-            // Create execution step
-            let step = Step {
-                tasks: ready_tasks.clone(),
-            };
+            // Keep the step contents deterministic regardless of map iteration order
+            ready_tasks.sort();
+            if let Some(order) = order {
+                // Stable, so tasks with the same hint stay sorted by id
+                ready_tasks.sort_by_key(|task_id| Reverse(order(task_id)));
+            }
+
+            // Exclusive tasks are split off into singleton steps after the shared one
+            let (exclusive, shared): (Vec<TaskId>, Vec<TaskId>) =
+                ready_tasks.iter().partition(|task_id| {
+                    tasks
+                        .get(*task_id)
+                        .is_some_and(|task| task.value().exclusive())
+                });
+            if !shared.is_empty() {
+                steps.push(Step { tasks: shared });
+            }
+            steps.extend(exclusive.into_iter().map(|task_id| Step {
+                tasks: vec![task_id],
+            }));
 
             // Record step mapping
             for task_id in &ready_tasks {
                 processed.insert(*task_id);
+                depths.insert(*task_id, depth);
             }
 
-            steps.push(step);
-
-            // Update in-degrees for dependent tasks
+            // Update in-degrees for dependent tasks, queueing the ones left with none
             for task_id in ready_tasks {
                 if let Some(dependents) = adjacency_list.get(&task_id) {
                     for dependent_id in dependents {
                         if let Some(degree) = in_degree.get_mut(dependent_id) {
                             *degree -= 1;
+                            if *degree == 0 {
+                                next_level.push(*dependent_id);
+                            }
                         }
                     }
                 }
@@ -89,22 +398,452 @@ impl Blueprint {
 
         // Check for circular dependencies
         if processed.len() != tasks.len() {
-            let remaining: Vec<TaskId> = tasks
+            let cycle = Self::find_cycle(tasks, &processed, ignore_missing)?;
+            return Err(BlueprintError::CircularDependency { cycle });
+        }
+
+        for dependents in adjacency_list.values_mut() {
+            dependents.sort();
+            dependents.dedup();
+        }
+
+        let names = tasks
+            .iter()
+            .filter_map(|v| v.value().name().cloned().map(|name| (*v.key(), name)))
+            .collect();
+        let costs = tasks
+            .iter()
+            .filter_map(|v| v.value().cost().map(|cost| (*v.key(), cost)))
+            .collect();
+
+        if let Some(stats) = stats {
+            *stats = BlueprintStats {
+                validation_ns: (validated - started).as_nanos(),
+                sort_ns: validated.elapsed().as_nanos(),
+                node_count: tasks.len(),
+                edge_count,
+            };
+        }
+
+        Ok(Blueprint {
+            task_to_step: Self::index_steps(&steps),
+            steps,
+            dependents: adjacency_list,
+            names,
+            depths,
+            costs,
+        })
+    }
+
+    /// Opt-in validation reporting structures that are legal to build, or that
+    /// [`from_tasks`](Self::from_tasks) only reports one at a time, but likely
+    /// mistakes: dangling references, self-dependencies, tasks that can never run
+    /// and, when there are several tasks, tasks unconnected to the others.
+    pub fn validate_strict<T, E>(tasks: &DashMap<TaskId, Task<T, E>>) -> Vec<BlueprintWarning> {
+        match Self::resolve_groups(tasks) {
+            Some((views, _)) => Self::strict_warnings(&views),
+            None => Self::strict_warnings(tasks),
+        }
+    }
+
+    fn strict_warnings<N: PlanNode>(tasks: &DashMap<TaskId, N>) -> Vec<BlueprintWarning> {
+        let mut task_ids: Vec<TaskId> = tasks.iter().map(|v| *v.key()).collect();
+        task_ids.sort();
+        let dependencies: HashMap<TaskId, Vec<TaskId>> = tasks
+            .iter()
+            .map(|v| {
+                (
+                    *v.key(),
+                    Self::ordering_dependencies(v.value(), tasks, false).collect(),
+                )
+            })
+            .collect();
+
+        let mut warnings = vec![];
+        let mut referenced = HashSet::new();
+        for task_id in &task_ids {
+            for dep_id in &dependencies[task_id] {
+                referenced.insert(*dep_id);
+                if dep_id == task_id {
+                    warnings.push(BlueprintWarning::SelfDependency(*task_id));
+                } else if !dependencies.contains_key(dep_id) {
+                    warnings.push(BlueprintWarning::DanglingDependency {
+                        task: *task_id,
+                        dependency: *dep_id,
+                    });
+                }
+            }
+        }
+
+        // A task can run once all of its dependencies can, repeat until nothing changes
+        let mut satisfiable = HashSet::new();
+        loop {
+            let newly_satisfiable: Vec<TaskId> = task_ids
                 .iter()
-                .map(|task_id| *task_id.key())
-                .filter(|id| !processed.contains(id))
+                .filter(|task_id| !satisfiable.contains(*task_id))
+                .filter(|task_id| {
+                    dependencies[*task_id]
+                        .iter()
+                        .all(|dep_id| satisfiable.contains(dep_id))
+                })
+                .copied()
                 .collect();
-            return Err(BlueprintError::CircularDependency(remaining));
+            if newly_satisfiable.is_empty() {
+                break;
+            }
+            satisfiable.extend(newly_satisfiable);
+        }
+        warnings.extend(
+            task_ids
+                .iter()
+                .filter(|task_id| !satisfiable.contains(*task_id))
+                .map(|task_id| BlueprintWarning::Unsatisfiable(*task_id)),
+        );
+
+        if task_ids.len() > 1 {
+            warnings.extend(
+                task_ids
+                    .iter()
+                    .filter(|task_id| {
+                        dependencies[*task_id].is_empty() && !referenced.contains(*task_id)
+                    })
+                    .map(|task_id| BlueprintWarning::Isolated(*task_id)),
+            );
         }
 
-        Ok(Blueprint { steps })
+        warnings
+    }
+
+    /// Collects every self-dependency and missing dependency, in task id order, and
+    /// then a circular dependency among the remaining edges, if there is one
+    pub(crate) fn validate_nodes<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+        ignore_missing: bool,
+    ) -> Vec<BlueprintError> {
+        if let Some((views, mut errors)) = Self::resolve_groups(tasks) {
+            errors.extend(Self::validate_nodes(&views, ignore_missing));
+            return errors;
+        }
+        let mut task_ids: Vec<TaskId> = tasks.iter().map(|v| *v.key()).collect();
+        task_ids.sort();
+
+        let mut errors = vec![];
+        // The planned edges, without the self-dependencies that are already reported
+        let edges: DashMap<TaskId, Dependency> = DashMap::new();
+        for task_id in task_ids {
+            let task = tasks.get(&task_id).unwrap();
+            let mut dependencies: Vec<TaskId> =
+                Self::ordering_dependencies(task.value(), tasks, ignore_missing).collect();
+            if dependencies.contains(&task_id) {
+                errors.push(BlueprintError::SelfDependency(task_id));
+            }
+            if !ignore_missing {
+                let mut missing: Vec<TaskId> = task
+                    .dependencies()
+                    .iter()
+                    .filter(|dep_id| !tasks.contains_key(dep_id))
+                    .collect();
+                missing.sort();
+                errors.extend(
+                    missing
+                        .into_iter()
+                        .map(|dep_id| BlueprintError::MissingDependency(task_id, dep_id)),
+                );
+            }
+            dependencies.retain(|dep_id| *dep_id != task_id && tasks.contains_key(dep_id));
+            edges.insert(task_id, dependencies.into());
+        }
+
+        if let Err(err @ BlueprintError::CircularDependency { .. }) =
+            Self::from_nodes(&edges, false)
+        {
+            errors.push(err);
+        }
+        errors
+    }
+
+    /// Copies of the tasks with their [`Dependency::Group`] dependencies resolved, with
+    /// an [`UnknownGroup`](BlueprintError::UnknownGroup) error for every group without
+    /// tasks, in task id order. `None` if no task depends on a group.
+    fn resolve_groups<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+    ) -> Option<(DashMap<TaskId, PlanView>, Vec<BlueprintError>)> {
+        if !tasks.iter().any(|v| v.value().dependencies().has_groups()) {
+            return None;
+        }
+        let groups = Self::group_members(tasks);
+        let mut task_ids: Vec<TaskId> = tasks.iter().map(|v| *v.key()).collect();
+        task_ids.sort();
+
+        let mut errors = vec![];
+        let views = DashMap::with_capacity(task_ids.len());
+        for task_id in task_ids {
+            let mut view = PlanView::of(tasks.get(&task_id).unwrap().value());
+            errors.extend(
+                view.dependencies
+                    .resolve_groups(&groups)
+                    .into_iter()
+                    .map(|group| BlueprintError::UnknownGroup(task_id, group)),
+            );
+            views.insert(task_id, view);
+        }
+        Some((views, errors))
+    }
+
+    /// The ids of the tasks of each group, in id order
+    pub(crate) fn group_members<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+    ) -> HashMap<String, Vec<TaskId>> {
+        let mut groups: HashMap<String, Vec<TaskId>> = HashMap::new();
+        for v in tasks.iter() {
+            if let Some(group) = v.value().group() {
+                groups.entry(group.clone()).or_default().push(*v.key());
+            }
+        }
+        for task_ids in groups.values_mut() {
+            task_ids.sort();
+        }
+        groups
+    }
+
+    /// Dependencies the task has to wait for: the strict ones, unless missing ones
+    /// are ignored, and the weak ones that are part of the plan
+    fn ordering_dependencies<'t, N: PlanNode>(
+        task: &'t N,
+        tasks: &'t DashMap<TaskId, N>,
+        ignore_missing: bool,
+    ) -> impl Iterator<Item = TaskId> + 't {
+        let dependencies = task.dependencies();
+        dependencies
+            .iter()
+            .filter(move |dep_id| !ignore_missing || tasks.contains_key(dep_id))
+            .chain(
+                dependencies
+                    .iter_weak()
+                    .filter(|dep_id| tasks.contains_key(dep_id)),
+            )
+    }
+
+    /// Walks the dependencies of the tasks left over by the topological sort.
+    /// Each of them still has an unprocessed dependency, so the walk is bound
+    /// to revisit a task, closing the cycle.
+    fn find_cycle<N: PlanNode>(
+        tasks: &DashMap<TaskId, N>,
+        processed: &HashSet<TaskId>,
+        ignore_missing: bool,
+    ) -> Result<Vec<TaskId>, BlueprintError> {
+        let unprocessed_dependency = |task_id: &TaskId| {
+            tasks
+                .get(task_id)
+                .and_then(|task| {
+                    Self::ordering_dependencies(task.value(), tasks, ignore_missing)
+                        .filter(|dep_id| !processed.contains(dep_id))
+                        .min()
+                })
+                .ok_or_else(|| {
+                    BlueprintError::InternalError(format!(
+                        "Task {task_id} has no unprocessed dependency while searching for a cycle"
+                    ))
+                })
+        };
+
+        let mut current = tasks
+            .iter()
+            .map(|v| *v.key())
+            .filter(|task_id| !processed.contains(task_id))
+            .min()
+            .ok_or_else(|| {
+                BlueprintError::InternalError("No unprocessed task left to search".to_string())
+            })?;
+        let mut path = vec![];
+        let mut positions = HashMap::new();
+
+        while !positions.contains_key(&current) {
+            positions.insert(current, path.len());
+            path.push(current);
+            current = unprocessed_dependency(&current)?;
+        }
+
+        // `path` follows "depends on" edges; flip it to run from dependency to dependent
+        let mut cycle = path.split_off(positions[&current]);
+        cycle.reverse();
+        Ok(cycle)
+    }
+
+    fn index_steps(steps: &[Step]) -> HashMap<TaskId, usize> {
+        steps
+            .iter()
+            .enumerate()
+            .flat_map(|(index, step)| step.tasks.iter().map(move |task_id| (*task_id, index)))
+            .collect()
+    }
+
+    /// The steps in execution order
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
     }
 
     pub fn step_count(&self) -> usize {
         self.steps.len()
     }
     pub fn tasks_at_step(&self, step: usize) -> Option<&[TaskId]> {
-        self.steps.get(step).map(|s| s.tasks.as_slice())
+        self.steps.get(step).map(Step::tasks)
+    }
+
+    /// Iterates over the steps in execution order, along with their index
+    pub fn iter_steps(&self) -> impl Iterator<Item = (usize, &[TaskId])> {
+        self.steps.iter().map(Step::tasks).enumerate()
+    }
+
+    /// Returns the index of the step the task is scheduled in
+    pub fn step_for_task(&self, id: &TaskId) -> Option<usize> {
+        self.task_to_step.get(id).copied()
+    }
+
+    /// Returns the depth of the task in the dependency graph, the length of the longest
+    /// chain of dependencies leading to it. It is the index of the task's step, unless
+    /// [exclusive](crate::Task::with_exclusive) tasks pushed it to a later step.
+    pub fn depth_of(&self, id: &TaskId) -> Option<usize> {
+        self.depths.get(id).copied()
+    }
+
+    /// Returns the depth of the deepest task, `0` for an empty plan
+    pub fn max_depth(&self) -> usize {
+        self.depths.values().copied().max().unwrap_or(0)
+    }
+
+    /// Returns the peak number of tasks that can run at once
+    pub fn max_width(&self) -> usize {
+        self.steps.iter().map(|s| s.tasks.len()).max().unwrap_or(0)
+    }
+
+    /// Returns the number of tasks in each step
+    pub fn width_per_step(&self) -> Vec<usize> {
+        self.steps.iter().map(|s| s.tasks.len()).collect()
+    }
+
+    /// Returns the tasks that directly depend on the given task
+    pub fn dependents_of(&self, id: &TaskId) -> &[TaskId] {
+        self.dependents
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the entry points of the plan, the tasks that wait for no other task,
+    /// in step order
+    pub fn roots(&self) -> Vec<TaskId> {
+        let dependents: HashSet<&TaskId> = self.dependents.values().flatten().collect();
+        self.steps
+            .iter()
+            .flat_map(|step| &step.tasks)
+            .filter(|task_id| !dependents.contains(task_id))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the terminal tasks of the plan, the tasks nothing waits for, in step order
+    pub fn leaves(&self) -> Vec<TaskId> {
+        self.steps
+            .iter()
+            .flat_map(|step| &step.tasks)
+            .filter(|task_id| self.dependents_of(task_id).is_empty())
+            .copied()
+            .collect()
+    }
+
+    /// Returns the longest dependency chain through the plan, from its first task
+    /// to its last, each task being a dependency of the next one. When tasks have a
+    /// [cost](crate::Task::with_cost), the chain with the highest total cost is picked,
+    /// tasks without one costing nothing. Otherwise every task counts the same.
+    pub fn critical_path(&self) -> Vec<TaskId> {
+        let weight = |task_id: &TaskId| {
+            if self.costs.is_empty() {
+                1
+            } else {
+                self.costs.get(task_id).map_or(0, Duration::as_nanos)
+            }
+        };
+        // Weight of the heaviest chain ending at each task, and its predecessor on that chain
+        let mut longest: HashMap<TaskId, (u128, Option<TaskId>)> = HashMap::new();
+        let mut end: Option<(u128, TaskId)> = None;
+
+        // Steps are in topological order, so every chain is final once its task is reached
+        for step in &self.steps {
+            for task_id in &step.tasks {
+                let length = longest.entry(*task_id).or_insert((weight(task_id), None)).0;
+                if end.is_none_or(|(best, _)| length > best) {
+                    end = Some((length, *task_id));
+                }
+
+                for dependent_id in self.dependents_of(task_id) {
+                    let through = length + weight(dependent_id);
+                    let entry = longest
+                        .entry(*dependent_id)
+                        .or_insert((weight(dependent_id), None));
+                    if through > entry.0 {
+                        *entry = (through, Some(*task_id));
+                    }
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut current = end.map(|(_, task_id)| task_id);
+        while let Some(task_id) = current {
+            path.push(task_id);
+            current = longest.get(&task_id).and_then(|(_, previous)| *previous);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Renders the plan as a Graphviz `digraph`, clustering tasks by step
+    /// and drawing an edge from every dependency to its dependent.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph blueprint {\n");
+
+        for (index, step) in self.steps.iter().enumerate() {
+            dot.push_str(&format!("    subgraph cluster_{index} {{\n"));
+            dot.push_str(&format!("        label = \"Step {index}\";\n"));
+            for task_id in &step.tasks {
+                match self.names.get(task_id) {
+                    Some(name) => dot.push_str(&format!(
+                        "        \"{task_id}\" [label = \"{}\"];\n",
+                        name.replace('"', "\\\"")
+                    )),
+                    None => dot.push_str(&format!("        \"{task_id}\";\n")),
+                }
+            }
+            dot.push_str("    }\n");
+        }
+
+        for step in &self.steps {
+            for task_id in &step.tasks {
+                for dependent_id in self.dependents_of(task_id) {
+                    dot.push_str(&format!("    \"{task_id}\" -> \"{dependent_id}\";\n"));
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Drops a task from the plan, removing its step if it becomes empty
+    pub(crate) fn remove_task(&mut self, id: &TaskId) {
+        for step in &mut self.steps {
+            step.tasks.retain(|task_id| task_id != id);
+        }
+        self.steps.retain(|step| !step.tasks.is_empty());
+        self.task_to_step = Self::index_steps(&self.steps);
+        self.dependents.remove(id);
+        self.names.remove(id);
+        self.depths.remove(id);
+        self.costs.remove(id);
+        for dependents in self.dependents.values_mut() {
+            dependents.retain(|task_id| task_id != id);
+        }
     }
 }
 
@@ -137,6 +876,174 @@ mod tests {
         assert_eq!(blueprint.tasks_at_step(0).unwrap().len(), 2);
     }
 
+    #[test]
+    fn test_self_dependency() {
+        let tasks = DashMap::new();
+        let task_id = TaskId::generate();
+        let task = Task::new(future::ready(Ok::<(), ()>(())), task_id).with_id(task_id);
+        tasks.insert(task_id, task);
+
+        assert!(matches!(
+            Blueprint::from_tasks(&tasks),
+            Err(BlueprintError::SelfDependency(id)) if id == task_id
+        ));
+    }
+
+    #[test]
+    fn test_validate_strict() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), id1);
+        let id2 = *task2.id();
+        let isolated = create_dummy_task();
+        let isolated_id = *isolated.id();
+        let unknown_id = TaskId::generate();
+        let dangling = Task::new(future::ready(Ok(())), unknown_id);
+        let dangling_id = *dangling.id();
+
+        tasks.insert(id1, task1);
+        tasks.insert(id2, task2);
+        assert!(Blueprint::validate_strict(&tasks).is_empty());
+
+        tasks.insert(isolated_id, isolated);
+        tasks.insert(dangling_id, dangling);
+        let warnings = Blueprint::validate_strict(&tasks);
+
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.contains(&BlueprintWarning::DanglingDependency {
+            task: dangling_id,
+            dependency: unknown_id,
+        }));
+        assert!(warnings.contains(&BlueprintWarning::Unsatisfiable(dangling_id)));
+        assert!(warnings.contains(&BlueprintWarning::Isolated(isolated_id)));
+    }
+
+    #[test]
+    fn test_iter_steps() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), id1);
+        let id2 = *task2.id();
+        let task3 = Task::new(future::ready(Ok(())), id2);
+        let id3 = *task3.id();
+        tasks.insert(id1, task1);
+        tasks.insert(id2, task2);
+        tasks.insert(id3, task3);
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        let steps: Vec<_> = blueprint.iter_steps().collect();
+
+        assert_eq!(steps, [(0, &[id1][..]), (1, &[id2][..]), (2, &[id3][..])]);
+        assert!(blueprint.steps().iter().all(|step| step.len() == 1));
+    }
+
+    #[test]
+    fn test_wide_graph_levels() {
+        let tasks = DashMap::new();
+        let roots: Vec<TaskId> = (0..20_000)
+            .map(|_| {
+                let task = create_dummy_task();
+                let task_id = *task.id();
+                tasks.insert(task_id, task);
+                task_id
+            })
+            .collect();
+        // Each task of the second level waits on two roots
+        let second_level: Vec<TaskId> = roots
+            .chunks(2)
+            .map(|pair| {
+                let task = Task::new(future::ready(Ok(())), pair.to_vec());
+                let task_id = *task.id();
+                tasks.insert(task_id, task);
+                task_id
+            })
+            .collect();
+        let sink = Task::new(future::ready(Ok(())), second_level.clone());
+        let sink_id = *sink.id();
+        tasks.insert(sink_id, sink);
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+
+        assert_eq!(blueprint.step_count(), 3);
+        assert_eq!(blueprint.steps()[0].len(), roots.len());
+        assert_eq!(blueprint.steps()[1].len(), second_level.len());
+        assert_eq!(blueprint.tasks_at_step(2), Some(&[sink_id][..]));
+    }
+
+    #[test]
+    fn test_from_tasks_partial() {
+        let tasks = DashMap::new();
+        let first = create_dummy_task();
+        let first_id = *first.id();
+        let second = Task::new(future::ready(Ok(())), first_id);
+        let second_id = *second.id();
+        let (cycle_a, cycle_b) = (TaskId::generate(), TaskId::generate());
+        let after_cycle = Task::new(future::ready(Ok(())), cycle_a);
+        let after_cycle_id = *after_cycle.id();
+        tasks.insert(first_id, first);
+        tasks.insert(second_id, second);
+        tasks.insert(
+            cycle_a,
+            Task::new(future::ready(Ok(())), cycle_b).with_id(cycle_a),
+        );
+        tasks.insert(
+            cycle_b,
+            Task::new(future::ready(Ok(())), cycle_a).with_id(cycle_b),
+        );
+        tasks.insert(after_cycle_id, after_cycle);
+
+        let (blueprint, errors) = Blueprint::from_tasks_partial(&tasks);
+
+        assert_eq!(
+            blueprint.iter_steps().collect::<Vec<_>>(),
+            [(0, &[first_id][..]), (1, &[second_id][..])]
+        );
+        assert_eq!(blueprint.step_for_task(&after_cycle_id), None);
+        assert_eq!(errors.len(), 1);
+        let BlueprintError::CircularDependency { cycle } = &errors[0] else {
+            panic!("Expected a circular dependency");
+        };
+        assert_eq!(cycle.len(), 2);
+        assert!(Blueprint::from_tasks(&tasks).is_err());
+    }
+
+    #[test]
+    fn test_from_tasks_partial_with_groups() {
+        let tasks = DashMap::new();
+        let member = create_dummy_task().with_group("ingest");
+        let member_id = *member.id();
+        let grouped = Task::new(
+            future::ready(Ok(())),
+            Dependency::Group("ingest".to_string()),
+        );
+        let grouped_id = *grouped.id();
+        let orphan = Task::new(
+            future::ready(Ok(())),
+            Dependency::Group("unknown".to_string()),
+        );
+        let orphan_id = *orphan.id();
+        let after_orphan = Task::new(future::ready(Ok(())), orphan_id);
+        let after_orphan_id = *after_orphan.id();
+        for task in [member, grouped, orphan, after_orphan] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let (blueprint, errors) = Blueprint::from_tasks_partial(&tasks);
+
+        assert_eq!(
+            blueprint.iter_steps().collect::<Vec<_>>(),
+            [(0, &[member_id][..]), (1, &[grouped_id][..])]
+        );
+        assert_eq!(blueprint.step_for_task(&orphan_id), None);
+        assert_eq!(blueprint.step_for_task(&after_orphan_id), None);
+        assert!(matches!(
+            &errors[..],
+            [BlueprintError::UnknownGroup(task_id, group)] if *task_id == orphan_id && group == "unknown"
+        ));
+    }
+
     #[test]
     fn test_sequential_blueprint() {
         let tasks = DashMap::new();
@@ -154,4 +1061,350 @@ mod tests {
         assert_eq!(blueprint.tasks_at_step(0).unwrap().len(), 1);
         assert_eq!(blueprint.tasks_at_step(1).unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_from_dependencies() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), vec![id1]);
+        let id2 = *task2.id();
+        let task3 = Task::new(future::ready(Ok(())), vec![id1, id2]);
+        let task4 = create_dummy_task();
+        for task in [task1, task2, task3, task4] {
+            tasks.insert(*task.id(), task);
+        }
+        let dependencies: HashMap<TaskId, Dependency> = tasks
+            .iter()
+            .map(|v| (*v.key(), v.value().dependencies().clone()))
+            .collect();
+
+        let from_tasks = Blueprint::from_tasks(&tasks).unwrap();
+        let from_dependencies = Blueprint::from_dependencies(&dependencies).unwrap();
+        let steps = |blueprint: &Blueprint| {
+            blueprint
+                .iter_steps()
+                .map(|(_, tasks)| tasks.to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(steps(&from_dependencies), steps(&from_tasks));
+        assert_eq!(from_dependencies.step_count(), 3);
+
+        let missing = HashMap::from([(id1, Dependency::Task(id2))]);
+        assert!(matches!(
+            Blueprint::from_dependencies(&missing),
+            Err(BlueprintError::MissingDependency(task, dep)) if task == id1 && dep == id2
+        ));
+    }
+
+    #[test]
+    fn test_exclusive_task_gets_own_step() {
+        let tasks = DashMap::new();
+        let shared = create_dummy_task();
+        let exclusive = create_dummy_task().with_exclusive(true);
+        let shared_id = *shared.id();
+        let exclusive_id = *exclusive.id();
+        let dependent = Task::new(future::ready(Ok(())), vec![exclusive_id]);
+        let dependent_id = *dependent.id();
+        for task in [shared, exclusive, dependent] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.step_count(), 3);
+        assert_eq!(blueprint.tasks_at_step(0).unwrap(), [shared_id]);
+        assert_eq!(blueprint.tasks_at_step(1).unwrap(), [exclusive_id]);
+        assert_eq!(blueprint.tasks_at_step(2).unwrap(), [dependent_id]);
+    }
+
+    #[test]
+    fn test_step_equality() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), vec![id1]);
+        let id2 = *task2.id();
+        tasks.insert(id1, task1);
+        tasks.insert(id2, task2);
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.steps[0], Step { tasks: vec![id1] });
+        assert_ne!(blueprint.steps[0], blueprint.steps[1]);
+        assert!(blueprint.steps[1].contains(&id2));
+        assert!(!blueprint.steps[1].contains(&id1));
+    }
+
+    #[test]
+    fn test_depth() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), vec![id1]);
+        let id2 = *task2.id();
+        let task3 = Task::new(future::ready(Ok(())), vec![id2]);
+        let id3 = *task3.id();
+        let exclusive = create_dummy_task().with_exclusive(true);
+        let exclusive_id = *exclusive.id();
+        for task in [task1, task2, task3, exclusive] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.depth_of(&id1), Some(0));
+        assert_eq!(blueprint.depth_of(&id2), Some(1));
+        assert_eq!(blueprint.depth_of(&id3), Some(2));
+        assert_eq!(blueprint.max_depth(), 2);
+        assert_eq!(blueprint.depth_of(&exclusive_id), Some(0));
+        assert_eq!(blueprint.step_for_task(&exclusive_id), Some(1));
+        assert_eq!(blueprint.depth_of(&TaskId::generate()), None);
+
+        let empty = Blueprint::from_tasks(&DashMap::<TaskId, Task<(), ()>>::new()).unwrap();
+        assert_eq!(empty.max_depth(), 0);
+    }
+
+    #[test]
+    fn test_from_tasks_timed() {
+        let tasks = DashMap::new();
+        // Diamond: a -> (b, c) -> d, plus an isolated task
+        let task_a = create_dummy_task();
+        let id_a = *task_a.id();
+        let task_b = Task::new(future::ready(Ok(())), [id_a]);
+        let task_c = Task::new(future::ready(Ok(())), [id_a]);
+        let task_d = Task::new(future::ready(Ok(())), [*task_b.id(), *task_c.id()]);
+        for task in [task_a, task_b, task_c, task_d, create_dummy_task()] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let (blueprint, stats) = Blueprint::from_tasks_timed(&tasks).unwrap();
+        assert_eq!(blueprint.step_count(), 3);
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.edge_count, 4);
+    }
+
+    #[test]
+    fn test_step_for_task() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), vec![id1]);
+        let id2 = *task2.id();
+
+        tasks.insert(id1, task1);
+        tasks.insert(id2, task2);
+
+        // Resolved through the public re-export
+        let blueprint = crate::Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.step_for_task(&id1), Some(0));
+        assert_eq!(blueprint.step_for_task(&id2), Some(1));
+        assert_eq!(blueprint.step_for_task(&TaskId::generate()), None);
+    }
+
+    #[test]
+    fn test_width() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        tasks.insert(id1, task1);
+        for _ in 0..3 {
+            let task = Task::new(future::ready(Ok(())), [id1]);
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.width_per_step(), vec![1, 3]);
+        assert_eq!(blueprint.max_width(), 3);
+
+        let empty = Blueprint::from_tasks(&DashMap::<TaskId, Task<(), ()>>::new()).unwrap();
+        assert!(empty.width_per_step().is_empty());
+        assert_eq!(empty.max_width(), 0);
+    }
+
+    #[test]
+    fn test_step_order_is_sorted() {
+        let tasks = DashMap::new();
+        let mut ids = vec![];
+        for _ in 0..16 {
+            let task = create_dummy_task();
+            ids.push(*task.id());
+            tasks.insert(*task.id(), task);
+        }
+        ids.sort();
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.tasks_at_step(0).unwrap(), ids.as_slice());
+    }
+
+    #[test]
+    fn test_from_tasks_ordered() {
+        let tasks = DashMap::new();
+        let root = create_dummy_task();
+        let root_id = *root.id();
+        tasks.insert(root_id, root);
+        for priority in [1, 5, -3, 5] {
+            let task = Task::new(future::ready(Ok(())), vec![root_id]).with_priority(priority);
+            tasks.insert(*task.id(), task);
+        }
+        let priority = |task_id: &TaskId| *tasks.get(task_id).unwrap().priority();
+
+        let blueprint = Blueprint::from_tasks_ordered(&tasks, priority).unwrap();
+        let step = blueprint.tasks_at_step(1).unwrap();
+        let priorities: Vec<_> = step.iter().map(priority).collect();
+        assert_eq!(priorities, [5, 5, 1, -3]);
+        assert!(step[0] < step[1]);
+        assert_eq!(blueprint.tasks_at_step(0).unwrap(), [root_id]);
+    }
+
+    #[test]
+    fn test_circular_dependency_path() {
+        let (id1, id2, id3) = (TaskId::generate(), TaskId::generate(), TaskId::generate());
+        let tasks = DashMap::new();
+        tasks.insert(id1, Task::new(future::ready(Ok::<(), ()>(())), [id3]));
+        tasks.insert(id2, Task::new(future::ready(Ok(())), [id1]));
+        tasks.insert(id3, Task::new(future::ready(Ok(())), [id2]));
+
+        // Downstream of the cycle, but not part of it
+        let task4 = Task::new(future::ready(Ok(())), [id1]);
+        let id4 = *task4.id();
+        tasks.insert(id4, task4);
+
+        let Err(BlueprintError::CircularDependency { mut cycle }) = Blueprint::from_tasks(&tasks)
+        else {
+            panic!("Expected a circular dependency");
+        };
+
+        assert_eq!(cycle.len(), 3);
+        assert!(!cycle.contains(&id4));
+        // Each task is a dependency of the next one, wrapping around
+        let start = cycle.iter().position(|id| *id == id1).unwrap();
+        cycle.rotate_left(start);
+        assert_eq!(cycle, vec![id1, id2, id3]);
+
+        let err = BlueprintError::CircularDependency { cycle };
+        assert_eq!(
+            err.to_string(),
+            format!("Circular dependency detected: {id1} -> {id2} -> {id3} -> {id1}")
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let tasks = DashMap::new();
+        let task1 = create_dummy_task();
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok(())), [id1]).with_name("second");
+        let id2 = *task2.id();
+        let task3 = Task::new(future::ready(Ok(())), [id1, id2]);
+        let id3 = *task3.id();
+
+        tasks.insert(id1, task1);
+        tasks.insert(id2, task2);
+        tasks.insert(id3, task3);
+
+        let dot = Blueprint::from_tasks(&tasks).unwrap().to_dot();
+
+        assert!(dot.starts_with("digraph blueprint {"));
+        assert_eq!(dot.matches(" -> ").count(), 3);
+        assert_eq!(dot.matches("subgraph cluster_").count(), 3);
+        assert!(dot.contains(&format!("\"{id1}\" -> \"{id2}\";")));
+        assert!(dot.contains(&format!("\"{id1}\" -> \"{id3}\";")));
+        assert!(dot.contains(&format!("\"{id2}\" -> \"{id3}\";")));
+        assert!(dot.contains(&format!("\"{id2}\" [label = \"second\"];")));
+    }
+
+    #[test]
+    fn test_dependents_of() {
+        let tasks = DashMap::new();
+        let task_a = create_dummy_task();
+        let id_a = *task_a.id();
+        let task_b = Task::new(future::ready(Ok(())), [id_a]);
+        let id_b = *task_b.id();
+        let task_c = Task::new(future::ready(Ok(())), [id_a]);
+        let id_c = *task_c.id();
+
+        tasks.insert(id_a, task_a);
+        tasks.insert(id_b, task_b);
+        tasks.insert(id_c, task_c);
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+
+        let mut expected = vec![id_b, id_c];
+        expected.sort();
+        assert_eq!(blueprint.dependents_of(&id_a), expected.as_slice());
+        assert!(blueprint.dependents_of(&id_b).is_empty());
+        assert!(blueprint.dependents_of(&TaskId::generate()).is_empty());
+    }
+
+    #[test]
+    fn test_roots_and_leaves() {
+        let tasks = DashMap::new();
+
+        // Diamond: a -> (b, c) -> d
+        let task_a = create_dummy_task();
+        let id_a = *task_a.id();
+        let task_b = Task::new(future::ready(Ok(())), [id_a]);
+        let task_c = Task::new(future::ready(Ok(())), [id_a]);
+        let task_d = Task::new(future::ready(Ok(())), [*task_b.id(), *task_c.id()]);
+        let id_d = *task_d.id();
+
+        for task in [task_a, task_b, task_c, task_d] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.roots(), [id_a]);
+        assert_eq!(blueprint.leaves(), [id_d]);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let tasks = DashMap::new();
+
+        // Three-deep chain: a -> b -> c
+        let task_a = create_dummy_task();
+        let id_a = *task_a.id();
+        let task_b = Task::new(future::ready(Ok(())), [id_a]);
+        let id_b = *task_b.id();
+        let task_c = Task::new(future::ready(Ok(())), [id_b]);
+        let id_c = *task_c.id();
+
+        // Parallel short chains: d -> e and f
+        let task_d = create_dummy_task();
+        let id_d = *task_d.id();
+        let task_e = Task::new(future::ready(Ok(())), [id_d]);
+        let task_f = create_dummy_task();
+
+        for task in [task_a, task_b, task_c, task_d, task_e, task_f] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.critical_path(), vec![id_a, id_b, id_c]);
+
+        let empty = Blueprint::from_tasks(&DashMap::<TaskId, Task<(), ()>>::new()).unwrap();
+        assert!(empty.critical_path().is_empty());
+    }
+
+    #[test]
+    fn test_weighted_critical_path() {
+        let tasks = DashMap::new();
+        let cost = Duration::from_millis;
+
+        // Long but light chain: a -> b -> c
+        let task_a = create_dummy_task().with_cost(cost(10));
+        let task_b = Task::new(future::ready(Ok(())), [*task_a.id()]).with_cost(cost(10));
+        let task_c = Task::new(future::ready(Ok(())), [*task_b.id()]).with_cost(cost(10));
+
+        // Short but heavy chain: d -> e
+        let task_d = create_dummy_task().with_cost(cost(50));
+        let id_d = *task_d.id();
+        let task_e = Task::new(future::ready(Ok(())), [id_d]).with_cost(cost(5));
+        let id_e = *task_e.id();
+
+        for task in [task_a, task_b, task_c, task_d, task_e] {
+            tasks.insert(*task.id(), task);
+        }
+
+        let blueprint = Blueprint::from_tasks(&tasks).unwrap();
+        assert_eq!(blueprint.critical_path(), vec![id_d, id_e]);
+    }
 }
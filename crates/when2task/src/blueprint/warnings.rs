@@ -0,0 +1,34 @@
+use crate::TaskId;
+use std::fmt::{Display, Formatter};
+
+/// Suspicious structures reported by [`Blueprint::validate_strict`](crate::Blueprint::validate_strict)
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlueprintWarning {
+    /// The task depends on an id that no task has
+    DanglingDependency { task: TaskId, dependency: TaskId },
+
+    /// The task lists itself among its dependencies
+    SelfDependency(TaskId),
+
+    /// The task can never run, as one of its direct or transitive
+    /// dependencies is missing or part of a cycle
+    Unsatisfiable(TaskId),
+
+    /// The task neither depends on nor is depended upon by any other task
+    Isolated(TaskId),
+}
+
+impl Display for BlueprintWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlueprintWarning::DanglingDependency { task, dependency } => {
+                write!(f, "Task {task} depends on unknown task {dependency}")
+            }
+            BlueprintWarning::SelfDependency(task) => write!(f, "Task {task} depends on itself"),
+            BlueprintWarning::Unsatisfiable(task) => write!(f, "Task {task} can never run"),
+            BlueprintWarning::Isolated(task) => {
+                write!(f, "Task {task} is not connected to any other task")
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::TaskId;
+use crate::{Task, TaskId};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub enum Dependency {
@@ -9,6 +10,19 @@ pub enum Dependency {
     /// Depends on a single specific task
     Task(TaskId),
 
+    /// Runs after the task if it is part of the execution, but neither requires
+    /// it to exist nor to succeed
+    Weak(TaskId),
+
+    /// Depends on every task of the group, see [`Task::with_group`]. Resolved to the
+    /// ids of the group's tasks whenever the tasks are planned, and replaced by them when
+    /// a [`TaskExecutorBuilder`](crate::TaskExecutorBuilder) builds the executor; it
+    /// contributes no id to [`iter`](Self::iter) until then. Planning fails with
+    /// [`BlueprintError::UnknownGroup`](crate::BlueprintError::UnknownGroup) if no task
+    /// belongs to the group. Tasks created with [`Task::with_inputs`] don't receive the
+    /// outputs of the group.
+    Group(String),
+
     // /// All dependencies must be satisfied (most common case)
     // All(Vec<Dependency>),
 
@@ -29,17 +43,114 @@ impl Dependency {
     pub fn and(self, dependency: impl Into<Dependency>) -> Self {
         Self::Combine(Box::new(self), Box::new(dependency.into()))
     }
-    /// Check if this dependency is satisfied given a set of completed tasks
-    pub fn is_satisfied(&self, completed_tasks: &std::collections::HashSet<TaskId>) -> bool {
-        match self {
-            Dependency::None => true,
-            Dependency::Task(id) => completed_tasks.contains(id),
-            // Dependency::All(deps) => deps.iter().all(|d| d.is_satisfied(completed_tasks)),
-            // Dependency::Not(dep) => !dep.is_satisfied(completed_tasks),
-            Dependency::Combine(a, b) => {
-                a.is_satisfied(completed_tasks) && b.is_satisfied(completed_tasks)
+    /// Depends on all the given tasks, like the list of their ids. Lists of task
+    /// references can't be converted with `into()`, as lists of ids already are.
+    pub fn on_tasks<'t, 'a: 't, T: 't, E: 't>(
+        tasks: impl IntoIterator<Item = &'t Task<'a, T, E>>,
+    ) -> Self {
+        tasks
+            .into_iter()
+            .map(|task| *task.id())
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Depends on all the borrowed ids, e.g. of a slice or a `&Vec<TaskId>`, without
+    /// copying them into a new collection first. Like [`on_tasks`](Self::on_tasks), this
+    /// can't be a `From` conversion, as collections of owned ids already are.
+    pub fn on_ids<'t>(task_ids: impl IntoIterator<Item = &'t TaskId>) -> Self {
+        task_ids.into_iter().copied().into()
+    }
+
+    /// Combines all the given dependencies into a balanced tree, so that its depth
+    /// grows logarithmically with the number of dependencies rather than linearly
+    /// like chained [`and`](Self::and) calls.
+    pub fn all(dependencies: impl IntoIterator<Item = Dependency>) -> Self {
+        let mut level: Vec<Dependency> = dependencies.into_iter().collect();
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            let mut pending = level.into_iter();
+            while let Some(first) = pending.next() {
+                next.push(match pending.next() {
+                    Some(second) => first.and(second),
+                    None => first,
+                });
             }
+            level = next;
         }
+        level.pop().unwrap_or_default()
+    }
+
+    /// Replaces every [`Group`](Dependency::Group) by a dependency on the ids of its tasks,
+    /// returning the names of the groups that have no tasks, which are replaced by nothing
+    pub(crate) fn resolve_groups(&mut self, groups: &HashMap<String, Vec<TaskId>>) -> Vec<String> {
+        let mut unknown = vec![];
+        let mut stack = vec![self];
+        while let Some(dependency) = stack.pop() {
+            match dependency {
+                Dependency::Group(name) => match groups.get(name) {
+                    Some(task_ids) => *dependency = task_ids.iter().copied().into(),
+                    None => {
+                        unknown.push(std::mem::take(name));
+                        *dependency = Dependency::None;
+                    }
+                },
+                Dependency::Combine(a, b) => {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                _ => {}
+            }
+        }
+        unknown
+    }
+
+    /// Drops every strict or weak dependency on `task_id`, e.g. once the task is removed
+    pub(crate) fn remove_task(&mut self, task_id: &TaskId) {
+        let mut stack = vec![self];
+        while let Some(dependency) = stack.pop() {
+            match dependency {
+                Dependency::Task(id) | Dependency::Weak(id) if id == task_id => {
+                    *dependency = Dependency::None;
+                }
+                Dependency::Combine(a, b) => {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the dependency refers to a [`Group`](Dependency::Group) anywhere
+    pub(crate) fn has_groups(&self) -> bool {
+        let mut stack = vec![self];
+        while let Some(dependency) = stack.pop() {
+            match dependency {
+                Dependency::Group(_) => return true,
+                Dependency::Combine(a, b) => {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Check if this dependency is satisfied given a set of completed tasks,
+    /// whatever their outcome. Walks the tree with an explicit stack, so deep
+    /// trees can't overflow the call stack.
+    pub fn is_satisfied(&self, completed_tasks: &std::collections::HashSet<TaskId>) -> bool {
+        self.is_satisfied_by(|id| completed_tasks.contains(id))
+    }
+
+    /// Like [`is_satisfied`](Self::is_satisfied), asking `is_completed` about each task,
+    /// e.g. to check against a `BTreeSet` or a slice
+    pub fn is_satisfied_by(&self, is_completed: impl Fn(&TaskId) -> bool) -> bool {
+        self.iter()
+            .chain(self.iter_weak())
+            .all(|id| is_completed(&id))
     }
 }
 
@@ -53,21 +164,29 @@ impl<'a> IntoIterator for &'a Dependency {
 }
 
 impl Dependency {
-    /// Returns an iterator over all TaskIds in this dependency
+    /// Returns an iterator over all the strict TaskIds in this dependency,
+    /// leaving out [`Weak`](Dependency::Weak) ones
     pub fn iter(&self) -> DependencyIter<'_> {
-        DependencyIter::new(self)
+        DependencyIter::new(self, false)
+    }
+
+    /// Returns an iterator over the TaskIds of the [`Weak`](Dependency::Weak) dependencies
+    pub fn iter_weak(&self) -> DependencyIter<'_> {
+        DependencyIter::new(self, true)
     }
 }
 
 /// Iterator over TaskIds in a Dependency
 pub struct DependencyIter<'a> {
     stack: Vec<&'a Dependency>,
+    weak: bool,
 }
 
 impl<'a> DependencyIter<'a> {
-    fn new(dependency: &'a Dependency) -> Self {
+    fn new(dependency: &'a Dependency, weak: bool) -> Self {
         Self {
             stack: vec![dependency],
+            weak,
         }
     }
 }
@@ -78,8 +197,10 @@ impl<'a> Iterator for DependencyIter<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(dep) = self.stack.pop() {
             match dep {
-                Dependency::None => continue,
-                Dependency::Task(task_id) => return Some(*task_id),
+                Dependency::None | Dependency::Group(_) => continue,
+                Dependency::Task(task_id) if !self.weak => return Some(*task_id),
+                Dependency::Weak(task_id) if self.weak => return Some(*task_id),
+                Dependency::Task(_) | Dependency::Weak(_) => continue,
                 Dependency::Combine(a, b) => {
                     self.stack.push(b);
                     self.stack.push(a);
@@ -96,13 +217,18 @@ impl From<TaskId> for Dependency {
     }
 }
 
+/// Depends on the task, without having to spell out its id
+impl<T, E> From<&Task<'_, T, E>> for Dependency {
+    fn from(task: &Task<'_, T, E>) -> Self {
+        Self::Task(*task.id())
+    }
+}
+
+/// No ids give [`None`](Dependency::None), a single id a plain [`Task`](Dependency::Task)
+/// and more ids a balanced tree, as built by [`Dependency::all`]
 impl<I: IntoIterator<Item = TaskId>> From<I> for Dependency {
     fn from(task_ids: I) -> Self {
-        let mut unit = Dependency::None;
-        for task in task_ids.into_iter().map(Dependency::from) {
-            unit = Dependency::Combine(Box::new(unit), Box::new(task));
-        }
-        unit
+        Dependency::all(task_ids.into_iter().map(Dependency::Task))
     }
 }
 
@@ -310,13 +436,7 @@ mod tests {
 
         // Test From<Vec<TaskId>> - single item
         let single_dep: Dependency = vec![task1].into();
-        match single_dep {
-            Dependency::Combine(none_box, task_box) => {
-                assert!(matches!(*none_box, Dependency::None));
-                assert!(matches!(*task_box, Dependency::Task(id) if id == task1));
-            }
-            _ => panic!("Expected Combine variant"),
-        }
+        assert_eq!(single_dep, Dependency::Task(task1));
 
         // Test From<Vec<TaskId>> - multiple items (all must be satisfied)
         let multi_dep: Dependency = vec![task1, task2, task3].into();
@@ -331,6 +451,94 @@ mod tests {
         assert!(!multi_dep.is_satisfied(&completed));
     }
 
+    #[test]
+    fn test_dependency_from_ids_shape() {
+        let (task1, task2, task3) = create_test_task_ids();
+        let combine = |a, b| Dependency::Combine(Box::new(a), Box::new(b));
+
+        let pair: Dependency = [task1, task2].into();
+        assert_eq!(
+            pair,
+            combine(Dependency::Task(task1), Dependency::Task(task2))
+        );
+
+        let triple: Dependency = [task1, task2, task3].into();
+        assert_eq!(
+            triple,
+            combine(
+                combine(Dependency::Task(task1), Dependency::Task(task2)),
+                Dependency::Task(task3)
+            )
+        );
+        assert_eq!(triple.iter().collect::<Vec<_>>(), vec![task1, task2, task3]);
+    }
+
+    #[test]
+    fn test_is_satisfied_by() {
+        let (task1, task2, task3) = create_test_task_ids();
+        let dependency = Dependency::Task(task1).and(Dependency::Weak(task2));
+
+        let completed = [task1, task2];
+        assert!(dependency.is_satisfied_by(|id| completed.contains(id)));
+        let completed = std::collections::BTreeSet::from([task1, task3]);
+        assert!(!dependency.is_satisfied_by(|id| completed.contains(id)));
+    }
+
+    #[test]
+    fn test_resolve_groups() {
+        let (task1, task2, task3) = create_test_task_ids();
+        let groups = HashMap::from([("ingest".to_string(), vec![task1, task2])]);
+
+        let mut dependency = Dependency::Task(task3)
+            .and(Dependency::Group("ingest".to_string()))
+            .and(Dependency::Group("unknown".to_string()));
+        assert_eq!(dependency.iter().collect::<Vec<_>>(), vec![task3]);
+        assert!(dependency.has_groups());
+
+        assert_eq!(dependency.resolve_groups(&groups), ["unknown"]);
+        assert!(!dependency.has_groups());
+        assert_eq!(
+            dependency.iter().collect::<Vec<_>>(),
+            vec![task3, task1, task2]
+        );
+    }
+
+    #[test]
+    fn test_dependency_from_borrowed_ids() {
+        let (task1, task2, _) = create_test_task_ids();
+        let ids = vec![task1, task2];
+
+        let from_slice = Dependency::on_ids(&ids[..]);
+        let from_vec = Dependency::on_ids(&ids);
+        assert_eq!(from_slice.iter().collect::<Vec<_>>(), ids);
+        assert_eq!(from_vec, from_slice);
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn test_dependency_from_task_references() {
+        let task_a = Task::new_independent(std::future::ready(Ok::<(), ()>(())));
+        let task_b = Task::new_independent(std::future::ready(Ok::<(), ()>(())));
+
+        let single = Dependency::from(&task_a);
+        assert_eq!(single, Dependency::Task(*task_a.id()));
+
+        let task_c = Task::new(
+            std::future::ready(Ok::<(), ()>(())),
+            Dependency::on_tasks([&task_a, &task_b]),
+        );
+        assert_eq!(
+            task_c.dependencies().iter().collect::<Vec<_>>(),
+            [*task_a.id(), *task_b.id()]
+        );
+
+        let chained = Dependency::from(&task_a).and(&task_b);
+        assert_eq!(
+            chained.iter().collect::<Vec<_>>(),
+            [*task_a.id(), *task_b.id()]
+        );
+    }
+
     #[test]
     fn test_dependency_traits_and_complex_scenarios() {
         let (task1, task2, task3) = create_test_task_ids();
@@ -372,4 +580,60 @@ mod tests {
         completed.insert(task3);
         assert!(complex_dep.is_satisfied(&completed));
     }
+
+    #[test]
+    fn test_dependency_all() {
+        fn depth(dependency: &Dependency) -> usize {
+            match dependency {
+                Dependency::Combine(a, b) => 1 + depth(a).max(depth(b)),
+                _ => 0,
+            }
+        }
+
+        assert!(matches!(Dependency::all([]), Dependency::None));
+
+        let task_ids: Vec<TaskId> = (0..1000).map(|_| TaskId::generate()).collect();
+        let dependency = Dependency::all(task_ids.iter().copied().map(Dependency::from));
+
+        assert_eq!(depth(&dependency), 10);
+        assert_eq!(dependency.iter().count(), 1000);
+
+        let mut completed: HashSet<TaskId> = task_ids.iter().copied().collect();
+        assert!(dependency.is_satisfied(&completed));
+
+        completed.remove(&task_ids[500]);
+        assert!(!dependency.is_satisfied(&completed));
+    }
+
+    #[test]
+    fn test_deep_dependency_chain() {
+        let task_ids: Vec<TaskId> = (0..100_000).map(|_| TaskId::generate()).collect();
+        // Chained `and` calls nest one level per task, unlike `Dependency::all`. Dropping
+        // a tree this deep recurses, so it is leaked instead.
+        let dependency = std::mem::ManuallyDrop::new(
+            task_ids
+                .iter()
+                .fold(Dependency::None, |chain, task_id| chain.and(*task_id)),
+        );
+
+        let mut completed: HashSet<TaskId> = task_ids.iter().copied().collect();
+        assert!(dependency.is_satisfied(&completed));
+
+        completed.remove(&task_ids[0]);
+        assert!(!dependency.is_satisfied(&completed));
+    }
+
+    #[test]
+    fn test_weak_dependency() {
+        let (task1, task2, _) = create_test_task_ids();
+        let dependency = Dependency::Task(task1).and(Dependency::Weak(task2));
+
+        assert_eq!(dependency.iter().collect::<Vec<_>>(), [task1]);
+        assert_eq!(dependency.iter_weak().collect::<Vec<_>>(), [task2]);
+
+        let mut completed = HashSet::from([task1]);
+        assert!(!dependency.is_satisfied(&completed));
+        completed.insert(task2);
+        assert!(dependency.is_satisfied(&completed));
+    }
 }
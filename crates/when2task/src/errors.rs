@@ -1,12 +1,40 @@
-use crate::TaskId;
+use crate::blueprint::BlueprintError;
 use thiserror::Error;
-use tokio::task::JoinError;
 
+/// Failure of the execution itself. Errors returned by the tasks are not
+/// execution errors, they are kept in the [`TaskResult`](crate::TaskResult)s.
 #[derive(Debug, Error)]
 pub enum ExecutionError {
-    #[error("Task {0} failed: {1}")]
-    TaskError(TaskId, String),
+    /// The tasks could not be planned
+    #[error("Blueprint error: {0}")]
+    Build(#[from] BlueprintError),
 
-    #[error("Join error: {0}")]
-    JoinError(#[from] JoinError),
+    #[error(
+        "The executor already ran its single-use tasks, only tasks created with Task::from_fn can be executed again"
+    )]
+    AlreadyExecuted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TaskId;
+
+    #[test]
+    fn test_display() {
+        let task_id = TaskId::generate();
+        let build = ExecutionError::from(BlueprintError::SelfDependency(task_id));
+        assert_eq!(
+            build.to_string(),
+            format!(
+                "Blueprint error: {}",
+                BlueprintError::SelfDependency(task_id)
+            )
+        );
+        assert!(
+            ExecutionError::AlreadyExecuted
+                .to_string()
+                .starts_with("The executor already ran its single-use tasks")
+        );
+    }
 }
@@ -0,0 +1,69 @@
+use crate::TaskId;
+use std::sync::{Arc, Mutex};
+
+/// Aborts a started task, whether it was spawned or is polled by the executor itself
+#[derive(Clone)]
+pub(crate) enum AbortHandle {
+    Spawned(tokio::task::AbortHandle),
+    Polled(futures::future::AbortHandle),
+}
+
+impl AbortHandle {
+    pub(crate) fn abort(&self) {
+        match self {
+            Self::Spawned(handle) => handle.abort(),
+            Self::Polled(handle) => handle.abort(),
+        }
+    }
+}
+
+/// Aborts the tasks of the step that is currently running, or a single one of them.
+///
+/// Obtained from [`TaskExecutor::step_abort_handle`](crate::TaskExecutor::step_abort_handle)
+/// before the execution starts. Aborted tasks are recorded as
+/// [`TaskOutcome::Cancelled`](crate::TaskOutcome::Cancelled) and their dependents as
+/// [`TaskOutcome::Skipped`](crate::TaskOutcome::Skipped), whether or not
+/// [`skip_on_failure`](crate::TaskExecutor::skip_on_failure) is enabled. A task polled by
+/// the executor stops at its next await point, so a blocking closure running in place (see
+/// [`Task::from_blocking`](crate::Task::from_blocking)) is only cancelled once it returns.
+/// Aborting between two steps has no effect.
+#[derive(Clone, Default)]
+pub struct StepAbortHandle {
+    handles: Arc<Mutex<Vec<(TaskId, AbortHandle)>>>,
+}
+
+impl StepAbortHandle {
+    /// Aborts every running task of the current step. With
+    /// [`ExecutionMode::eager`](crate::ExecutionMode::eager), which has no steps, aborts
+    /// every running task.
+    pub fn abort_step(&self) {
+        for (_, handle) in self.handles.lock().unwrap().iter() {
+            handle.abort();
+        }
+    }
+
+    /// Aborts a single running task, leaving the other tasks of the step running.
+    /// Returns whether the task was running, and so is cancelled.
+    pub fn cancel_task(&self, task_id: TaskId) -> bool {
+        let handles = self.handles.lock().unwrap();
+        let Some((_, handle)) = handles.iter().find(|(id, _)| *id == task_id) else {
+            return false;
+        };
+        handle.abort();
+        true
+    }
+
+    pub(crate) fn register(&self, task_id: TaskId, handle: AbortHandle) {
+        self.handles.lock().unwrap().push((task_id, handle));
+    }
+
+    /// Forgets the handle of a task that completed
+    pub(crate) fn forget(&self, task_id: &TaskId) {
+        self.handles.lock().unwrap().retain(|(id, _)| id != task_id);
+    }
+
+    /// Forgets the handles of a step that completed
+    pub(crate) fn clear(&self) {
+        self.handles.lock().unwrap().clear();
+    }
+}
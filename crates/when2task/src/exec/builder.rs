@@ -1,10 +1,29 @@
-use crate::blueprint::Blueprint;
+use crate::blueprint::{Blueprint, BlueprintError};
 use crate::{BuildError, ExecutionMode, Task, TaskExecutor, TaskId};
 use dashmap::DashMap;
 
+/// Collects tasks before planning them into a [`TaskExecutor`].
+///
+/// Tasks are inserted through a shared reference, so the builder is bound to a
+/// variable first and the inserts are chained off it:
+///
+/// ```
+/// use when2task::{ExecutionMode, Task, TaskExecutorBuilder};
+///
+/// let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+/// builder
+///     .insert(Task::new_independent(async { Ok::<_, ()>(1) }))
+///     .insert(Task::new_independent(async { Ok::<_, ()>(2) }));
+///
+/// let executor = builder.build().unwrap();
+/// assert_eq!(executor.len(), 2);
+/// ```
 pub struct TaskExecutorBuilder<T, E> {
     tasks: DashMap<TaskId, Task<'static, T, E>>,
     mode: ExecutionMode<T, E>,
+    ignore_missing_dependencies: bool,
+    blueprint: Option<Blueprint>,
+    max_step_fanout: Option<usize>,
 }
 
 impl<T, E> TaskExecutorBuilder<T, E> {
@@ -12,20 +31,505 @@ impl<T, E> TaskExecutorBuilder<T, E> {
         Self {
             tasks: Default::default(),
             mode: execution_mode,
+            ignore_missing_dependencies: false,
+            blueprint: None,
+            max_step_fanout: None,
         }
     }
+
+    /// Escape hatch for graphs assembled dynamically: dependencies on tasks that
+    /// were never inserted are dropped and treated as satisfied, instead of
+    /// failing the build with a missing dependency error. Off by default.
+    pub fn ignore_missing_dependencies(mut self, ignore: bool) -> Self {
+        self.ignore_missing_dependencies = ignore;
+        self
+    }
+
+    /// Reuses a blueprint planned earlier, e.g. for a previous run of the same graph,
+    /// instead of planning the tasks again when building. The build fails with
+    /// [`BuildError::BlueprintMismatch`] unless the blueprint has exactly the ids of the
+    /// inserted tasks. Their dependencies are not checked, they must be the ones the
+    /// blueprint was planned from.
+    pub fn with_blueprint(mut self, blueprint: Blueprint) -> Self {
+        self.blueprint = Some(blueprint);
+        self
+    }
+
+    /// Fails the build with [`BuildError::FanoutExceeded`] if a step of the plan has
+    /// more than `limit` tasks, to catch accidental fan-outs. Unlike
+    /// [`ExecutionMode::bounded`], this checks the plan rather than limiting how many
+    /// tasks run at once.
+    pub fn max_step_fanout(mut self, limit: usize) -> Self {
+        self.max_step_fanout = Some(limit);
+        self
+    }
+
+    /// Adds a task, replacing any task previously inserted with the same id
     pub fn insert(&self, task: Task<'static, T, E>) -> &Self {
         self.tasks.insert(*task.id(), task);
         self
     }
 
-    pub fn build(self) -> Result<TaskExecutor<T, E>, BuildError> {
-        let blueprint = Blueprint::from_tasks(&self.tasks)?;
+    /// Adds a task, failing with [`BuildError::DuplicateTask`] if a task with the
+    /// same id was already inserted. The existing task is kept in that case.
+    pub fn try_insert(&self, task: Task<'static, T, E>) -> Result<&Self, BuildError> {
+        if self.tasks.contains_key(task.id()) {
+            return Err(BuildError::DuplicateTask(*task.id()));
+        }
+        self.tasks.insert(*task.id(), task);
+        Ok(self)
+    }
+
+    /// Plans the inserted tasks without building the executor, reporting
+    /// missing or circular dependencies. No future is polled.
+    pub fn dry_run(&self) -> Result<Blueprint, BlueprintError> {
+        Blueprint::from_nodes(&self.tasks, self.ignore_missing_dependencies)
+    }
+
+    /// Like [`dry_run`](Self::dry_run), but reports every self-dependency and missing
+    /// dependency at once instead of stopping at the first one, followed by a
+    /// circular dependency if the remaining dependencies form one
+    pub fn validate(&self) -> Result<(), Vec<BlueprintError>> {
+        let errors = Blueprint::validate_nodes(&self.tasks, self.ignore_missing_dependencies);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like [`build`](Self::build), but fails with [`BuildError::EmptyExecutor`]
+    /// if no task was inserted
+    pub fn build_non_empty(self) -> Result<TaskExecutor<T, E>, BuildError> {
+        if self.tasks.is_empty() {
+            return Err(BuildError::EmptyExecutor);
+        }
+        self.build()
+    }
+
+    pub fn build(mut self) -> Result<TaskExecutor<T, E>, BuildError> {
+        resolve_groups(&self.tasks)?;
+        let blueprint = match self.blueprint.take() {
+            Some(blueprint) => {
+                let mismatch = self
+                    .tasks
+                    .iter()
+                    .map(|v| *v.key())
+                    .find(|task_id| blueprint.step_for_task(task_id).is_none())
+                    .or_else(|| {
+                        blueprint
+                            .task_to_step
+                            .keys()
+                            .find(|task_id| !self.tasks.contains_key(task_id))
+                            .copied()
+                    });
+                if let Some(task_id) = mismatch {
+                    return Err(BuildError::BlueprintMismatch(task_id));
+                }
+                blueprint
+            }
+            None => self.dry_run()?,
+        };
+        if let Some(limit) = self.max_step_fanout {
+            let crowded = blueprint
+                .iter_steps()
+                .find(|(_, tasks)| tasks.len() > limit);
+            if let Some((step, tasks)) = crowded {
+                return Err(BuildError::FanoutExceeded {
+                    step,
+                    count: tasks.len(),
+                    limit,
+                });
+            }
+        }
 
         Ok(TaskExecutor {
             mode: self.mode,
             tasks: self.tasks,
             blueprint,
+            skip_on_failure: false,
+            hooks: Default::default(),
+            abort_handle: Default::default(),
+            ignore_missing_dependencies: self.ignore_missing_dependencies,
+            concurrency: None,
+            stop: None,
+            consumed: Default::default(),
         })
     }
 }
+
+/// Replaces the [`Dependency::Group`](crate::Dependency::Group) dependencies of the tasks
+/// by the ids of the tasks of that group, in id order, so that the executor skips
+/// dependents of failed group members like for any other dependency
+pub(super) fn resolve_groups<T, E>(
+    tasks: &DashMap<TaskId, Task<'static, T, E>>,
+) -> Result<(), BlueprintError> {
+    if !tasks.iter().any(|v| v.value().dependencies().has_groups()) {
+        return Ok(());
+    }
+    let groups = Blueprint::group_members(tasks);
+    for mut v in tasks.iter_mut() {
+        v.value_mut().resolve_groups(&groups)?;
+    }
+    Ok(())
+}
+
+/// A builder using [`ExecutionMode::true_async`]
+impl<T, E> Default for TaskExecutorBuilder<T, E> {
+    fn default() -> Self {
+        Self::new(ExecutionMode::true_async())
+    }
+}
+
+/// An empty executor using [`ExecutionMode::true_async`], to which tasks are added with
+/// [`TaskExecutor::add_task`]
+impl<T, E> Default for TaskExecutor<T, E> {
+    fn default() -> Self {
+        TaskExecutorBuilder::default()
+            .build()
+            .expect("An executor without tasks has nothing to plan")
+    }
+}
+
+impl<T, E> Extend<Task<'static, T, E>> for TaskExecutorBuilder<T, E> {
+    fn extend<I: IntoIterator<Item = Task<'static, T, E>>>(&mut self, tasks: I) {
+        for task in tasks {
+            self.insert(task);
+        }
+    }
+}
+
+/// Collects tasks into a builder using [`ExecutionMode::true_async`]. Planning can
+/// fail, so the executor is still created with [`build`](TaskExecutorBuilder::build).
+impl<T, E> FromIterator<Task<'static, T, E>> for TaskExecutorBuilder<T, E> {
+    fn from_iter<I: IntoIterator<Item = Task<'static, T, E>>>(tasks: I) -> Self {
+        let mut builder = Self::default();
+        builder.extend(tasks);
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dependency, TaskOutcome};
+    use std::future;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_try_insert_duplicate() {
+        let task = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let task_id = *task.id();
+        let duplicate = Task::new_independent(future::ready(Ok::<i32, ()>(2))).with_id(task_id);
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.try_insert(task).unwrap();
+        let err = builder.try_insert(duplicate).err().unwrap();
+
+        assert!(matches!(err, BuildError::DuplicateTask(id) if id == task_id));
+        assert_eq!(builder.build().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_build_empty() {
+        let executor = TaskExecutorBuilder::<(), ()>::new(ExecutionMode::true_async())
+            .build()
+            .unwrap();
+        assert!(executor.is_empty());
+
+        let err = TaskExecutorBuilder::<(), ()>::new(ExecutionMode::true_async())
+            .build_non_empty()
+            .err()
+            .unwrap();
+        assert!(matches!(err, BuildError::EmptyExecutor));
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(Task::new_independent(future::ready(Ok::<(), ()>(()))));
+        assert_eq!(builder.build_non_empty().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dry_run_circular_dependency() {
+        let polled = Arc::new(AtomicBool::new(false));
+        let polling_task = |dependency: TaskId| {
+            let polled = polled.clone();
+            Task::new(
+                async move {
+                    polled.store(true, Ordering::SeqCst);
+                    Ok::<(), ()>(())
+                },
+                dependency,
+            )
+        };
+        let task_a_id = TaskId::generate();
+        let task_b = polling_task(task_a_id);
+        let task_a = polling_task(*task_b.id()).with_id(task_a_id);
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(task_a).insert(task_b);
+
+        assert!(matches!(
+            builder.dry_run(),
+            Err(BlueprintError::CircularDependency { .. })
+        ));
+        assert!(!polled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_with_blueprint() {
+        let first_id = TaskId::generate();
+        let second_id = TaskId::generate();
+        let build = |offset: i32| {
+            let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+            builder
+                .insert(Task::new_independent(async move { Ok::<_, ()>(offset) }).with_id(first_id))
+                .insert(Task::new(async move { Ok(offset + 1) }, first_id).with_id(second_id));
+            builder
+        };
+        let blueprint = build(0).dry_run().unwrap();
+
+        for offset in [10, 20] {
+            let executor = build(offset)
+                .with_blueprint(blueprint.clone())
+                .build()
+                .unwrap();
+            let result = executor.execute().await.unwrap();
+            assert_eq!(result.into_successful_values(), [offset, offset + 1]);
+        }
+
+        let builder = build(0);
+        let extra = Task::new_independent(future::ready(Ok(3)));
+        let extra_id = *extra.id();
+        builder.insert(extra);
+        assert!(matches!(
+            builder.with_blueprint(blueprint).build(),
+            Err(BuildError::BlueprintMismatch(id)) if id == extra_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ignore_missing_dependencies() {
+        let missing_dependency_task =
+            || Task::new(future::ready(Ok::<i32, ()>(1)), TaskId::generate());
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(missing_dependency_task());
+        assert!(matches!(
+            builder.build(),
+            Err(BuildError::BlueprintError(
+                BlueprintError::MissingDependency(..)
+            ))
+        ));
+
+        let builder =
+            TaskExecutorBuilder::new(ExecutionMode::true_async()).ignore_missing_dependencies(true);
+        builder.insert(missing_dependency_task());
+        let result = builder.build().unwrap().execute().await.unwrap();
+
+        assert_eq!(result.into_successful_values(), [1]);
+    }
+
+    #[test]
+    fn test_collect_and_extend() {
+        let tasks: Vec<_> = (0..3)
+            .map(|i| Task::new_independent(future::ready(Ok::<i32, ()>(i))))
+            .collect();
+        let first_id = *tasks[0].id();
+        let mut builder: TaskExecutorBuilder<_, _> = tasks.into_iter().collect();
+        builder.extend([Task::new(future::ready(Ok(3)), first_id)]);
+
+        let executor = builder.build().unwrap();
+
+        assert_eq!(executor.len(), 4);
+        assert_eq!(executor.dry_run().unwrap().step_count(), 2);
+    }
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        let (missing1, missing2) = (TaskId::generate(), TaskId::generate());
+        let first = Task::new(future::ready(Ok::<i32, ()>(1)), missing1);
+        let second = Task::new(future::ready(Ok::<i32, ()>(2)), missing2);
+        let mut expected = vec![(*first.id(), missing1), (*second.id(), missing2)];
+        expected.sort();
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(first).insert(second);
+        let errors = builder.validate().unwrap_err();
+
+        let reported: Vec<_> = errors
+            .iter()
+            .map(|err| match err {
+                BlueprintError::MissingDependency(task_id, dep_id) => (*task_id, *dep_id),
+                err => panic!("Unexpected error {err}"),
+            })
+            .collect();
+        assert_eq!(reported, expected);
+    }
+
+    #[test]
+    fn test_validate_cycle() {
+        let a = TaskId::generate();
+        let b = TaskId::generate();
+        let task_a = Task::new(future::ready(Ok::<i32, ()>(1)), b).with_id(a);
+        let task_b = Task::new(future::ready(Ok::<i32, ()>(2)), a).with_id(b);
+        let looping_id = TaskId::generate();
+        let looping = Task::new(future::ready(Ok::<i32, ()>(3)), looping_id).with_id(looping_id);
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(task_a).insert(task_b).insert(looping);
+        let errors = builder.validate().unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], BlueprintError::SelfDependency(id) if id == looping_id));
+        assert!(matches!(
+            errors[1],
+            BlueprintError::CircularDependency { .. }
+        ));
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        let task = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(task);
+
+        assert!(builder.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_group_dependency() {
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        for millis in [10, 30, 50] {
+            let ingest = Task::new_independent(async move {
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                Ok::<u64, ()>(millis)
+            });
+            builder.insert(ingest.with_group("ingest"));
+        }
+        let report = Task::new(
+            future::ready(Ok(0)),
+            Dependency::Group("ingest".to_string()),
+        );
+        let report_id = *report.id();
+        builder.insert(report);
+
+        let executor = builder.build().unwrap();
+        assert_eq!(
+            executor
+                .get_task(&report_id)
+                .unwrap()
+                .dependencies()
+                .iter()
+                .count(),
+            3
+        );
+
+        let result = executor.execute().await.unwrap();
+        let report_result = result.steps[1][0].as_completed().unwrap();
+        assert_eq!(report_result.task_id, report_id);
+        let ingested_at = result.steps[0]
+            .iter()
+            .filter_map(TaskOutcome::as_completed)
+            .map(|task_result| task_result.start_offset + task_result.duration)
+            .max()
+            .unwrap();
+        assert!(report_result.start_offset >= ingested_at);
+    }
+
+    #[test]
+    fn test_group_dependency_planning() {
+        let grouped_builder = || {
+            let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+            builder
+                .insert(Task::new_independent(future::ready(Ok::<i32, ()>(1))).with_group("ingest"))
+                .insert(Task::new(
+                    future::ready(Ok(2)),
+                    Dependency::Group("ingest".to_string()),
+                ));
+            builder
+        };
+
+        let builder = grouped_builder();
+        let blueprint = builder.dry_run().unwrap();
+        assert_eq!(blueprint.step_count(), 2);
+        assert!(builder.validate().is_ok());
+        let executor = builder.with_blueprint(blueprint.clone()).build().unwrap();
+        assert_eq!(executor.dry_run().unwrap().steps(), blueprint.steps());
+
+        let mut executor = TaskExecutor::default();
+        executor
+            .add_task(Task::new_independent(future::ready(Ok::<i32, ()>(1))).with_group("ingest"))
+            .unwrap();
+        let report = Task::new(
+            future::ready(Ok(2)),
+            Dependency::Group("ingest".to_string()),
+        );
+        let report_id = *report.id();
+        executor.add_task(report).unwrap();
+        assert_eq!(
+            executor.dry_run().unwrap().step_for_task(&report_id),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_unknown_group() {
+        let report = Task::new(
+            future::ready(Ok::<i32, ()>(1)),
+            Dependency::Group("ingest".to_string()),
+        );
+        let report_id = *report.id();
+        let is_unknown_group = |err: &BlueprintError| matches!(err, BlueprintError::UnknownGroup(id, group) if *id == report_id && group == "ingest");
+
+        let builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        builder.insert(report);
+        assert!(is_unknown_group(&builder.dry_run().err().unwrap()));
+        let errors = builder.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(is_unknown_group(&errors[0]));
+        assert!(matches!(
+            builder.build(),
+            Err(BuildError::BlueprintError(err)) if is_unknown_group(&err)
+        ));
+
+        let mut executor = TaskExecutor::default();
+        let report = Task::new(
+            future::ready(Ok::<i32, ()>(1)),
+            Dependency::Group("ingest".to_string()),
+        );
+        assert!(matches!(
+            executor.add_task(report),
+            Err(BuildError::BlueprintError(BlueprintError::UnknownGroup(..)))
+        ));
+        assert!(executor.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_default_executor() {
+        let mut executor = TaskExecutor::default();
+        executor
+            .add_task(Task::new_independent(future::ready(Ok::<i32, ()>(1))))
+            .unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.into_successful_values(), [1]);
+    }
+
+    #[test]
+    fn test_max_step_fanout() {
+        let mut builder = TaskExecutorBuilder::new(ExecutionMode::true_async()).max_step_fanout(3);
+        builder.extend((0..5).map(|i| Task::new_independent(future::ready(Ok::<i32, ()>(i)))));
+
+        assert!(matches!(
+            builder.build(),
+            Err(BuildError::FanoutExceeded {
+                step: 0,
+                count: 5,
+                limit: 3
+            })
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::TaskId;
 use crate::blueprint::BlueprintError;
 use thiserror::Error;
 
@@ -5,4 +6,26 @@ use thiserror::Error;
 pub enum BuildError {
     #[error("Blueprint error: {0}")]
     BlueprintError(#[from] BlueprintError),
+
+    #[error("Task {0} is already registered")]
+    DuplicateTask(TaskId),
+
+    #[error("Task has no future to execute")]
+    MissingFuture,
+
+    #[error("Executor has no tasks")]
+    EmptyExecutor,
+
+    #[error("Blueprint was planned for other tasks, task {0} is not part of both")]
+    BlueprintMismatch(TaskId),
+
+    #[error("Task {0} receives the outputs of its dependencies, its output type can't change")]
+    OutputTypeFixed(TaskId),
+
+    #[error("Step {step} has {count} tasks, more than the limit of {limit}")]
+    FanoutExceeded {
+        step: usize,
+        count: usize,
+        limit: usize,
+    },
 }
@@ -1,17 +1,152 @@
-use crate::blueprint::Blueprint;
-use crate::result::{ExecutionResult, TaskResult};
-use crate::{ExecutionError, ExecutionMode, Task, TaskId};
+use super::abort::AbortHandle;
+use super::builder::resolve_groups;
+use crate::blueprint::{Blueprint, BlueprintError};
+use crate::result::{ExecutionResult, StepTiming, TaskOutcome, TaskResult};
+use crate::task::BlockingRunner;
+use crate::{
+    BuildError, ExecutionError, ExecutionMode, ProgressSender, Spawner, StepAbortHandle, Task,
+    TaskId, UnitTask,
+};
 use dashmap::DashMap;
-use futures::FutureExt;
+use futures::future::Aborted;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use std::any::Any;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use tokio::task::JoinError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
-type StepHandle<T, E> = Pin<Box<dyn Future<Output = Result<TaskResult<T, E>, JoinError>>>>;
+/// How a single task of a step finished
+enum Completion<T, E> {
+    Finished(Result<T, E>),
+    TimedOut,
+    Panicked(String),
+    Cancelled,
+}
+
+type StepHandle<T, E> = Pin<Box<dyn Future<Output = Completion<T, E>>>>;
+
+pub(super) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Task panicked".to_string())
+}
+
+/// When a task's future was first polled and when it completed. Recorded from
+/// inside the future so that time spent waiting to be scheduled is not counted.
+#[derive(Default)]
+pub(super) struct Timing {
+    started: OnceLock<Instant>,
+    finished: OnceLock<Instant>,
+}
+
+impl Timing {
+    pub(super) async fn timed<F: Future>(task: F, timing: Arc<Timing>) -> F::Output {
+        timing.started.get_or_init(Instant::now);
+        let output = task.await;
+        timing.finished.get_or_init(Instant::now);
+        output
+    }
+
+    /// Returns the duration of the task and its start relative to `origin`
+    pub(super) fn span(&self, origin: Instant) -> (Duration, Duration) {
+        let started = self.started.get().copied().unwrap_or(origin);
+        let finished = self.finished.get().copied().unwrap_or(started);
+        (finished - started, started - origin)
+    }
+}
+
+/// Hands out the task to run for an id
+type TakeTask<'r, T, E> = &'r dyn Fn(&TaskId) -> Option<Task<'static, T, E>>;
+
+/// Running counts of an execution
+#[derive(Default)]
+struct Tally {
+    successful_tasks: usize,
+    failed_tasks: usize,
+    skipped_tasks: usize,
+    /// Tasks that failed or were skipped, used to propagate skipping downstream
+    unsatisfied: HashSet<TaskId>,
+    /// Tasks that were cancelled or skipped, whose dependents are skipped even without
+    /// [`TaskExecutor::skip_on_failure`]
+    cancelled: HashSet<TaskId>,
+}
+
+impl Tally {
+    fn record<T, E>(&mut self, outcome: &TaskOutcome<T, E>) {
+        match outcome {
+            TaskOutcome::Completed(task_result) if task_result.result.is_ok() => {
+                self.successful_tasks += 1;
+                return;
+            }
+            TaskOutcome::Skipped(_) => {
+                self.skipped_tasks += 1;
+                self.cancelled.insert(outcome.task_id());
+            }
+            TaskOutcome::Cancelled(_) => {
+                self.failed_tasks += 1;
+                self.cancelled.insert(outcome.task_id());
+            }
+            _ => self.failed_tasks += 1,
+        }
+        self.unsatisfied.insert(outcome.task_id());
+    }
+
+    /// Records a task skipped by its own condition, which doesn't hold up its dependents
+    fn record_unmet_condition<T, E>(&mut self, task_id: TaskId) -> TaskOutcome<T, E> {
+        self.skipped_tasks += 1;
+        TaskOutcome::Skipped(task_id)
+    }
+}
+
+type StepStartHook = Box<dyn Fn(usize) + Send + Sync>;
+type TaskStartHook = Arc<dyn Fn(TaskId) + Send + Sync>;
+type TaskCompleteHook<T, E> = Box<dyn Fn(&TaskResult<T, E>) + Send + Sync>;
+type StepCompleteHook<T, E> = Box<dyn Fn(usize, &[TaskOutcome<T, E>]) + Send + Sync>;
+type ProgressHook = Arc<dyn Fn(TaskId, f32) + Send + Sync>;
+
+/// Callbacks fired as the execution proceeds
+pub(crate) struct Hooks<T, E> {
+    step_start: Option<StepStartHook>,
+    task_start: Option<TaskStartHook>,
+    task_complete: Option<TaskCompleteHook<T, E>>,
+    step_complete: Option<StepCompleteHook<T, E>>,
+    progress: Option<ProgressHook>,
+}
+
+impl<T, E> Default for Hooks<T, E> {
+    fn default() -> Self {
+        Self {
+            step_start: None,
+            task_start: None,
+            task_complete: None,
+            step_complete: None,
+            progress: None,
+        }
+    }
+}
 
 pub struct TaskExecutor<T, E> {
     pub(crate) tasks: DashMap<TaskId, Task<'static, T, E>>,
     pub(crate) mode: ExecutionMode<T, E>,
     pub(crate) blueprint: Blueprint,
+    pub(crate) skip_on_failure: bool,
+    pub(crate) hooks: Hooks<T, E>,
+    pub(crate) abort_handle: StepAbortHandle,
+    pub(crate) ignore_missing_dependencies: bool,
+    pub(crate) concurrency: Option<Arc<Semaphore>>,
+    pub(crate) stop: Option<Arc<AtomicBool>>,
+    /// Set once an [`execute_ref`](Self::execute_ref) consumed single-use tasks
+    pub(crate) consumed: AtomicBool,
 }
 
 impl<T: 'static, E: 'static> TaskExecutor<T, E> {
@@ -19,58 +154,702 @@ impl<T: 'static, E: 'static> TaskExecutor<T, E> {
         self.tasks.iter().map(|v| *v.key()).collect()
     }
 
+    /// Adds a task after the executor was built, replanning the execution. A
+    /// [`Dependency::Group`](crate::Dependency::Group) of the task is resolved to the
+    /// tasks of the group the executor has at that point.
+    ///
+    /// Fails without modifying the executor if a task with the same id exists
+    /// or if the task's dependencies can't be planned.
+    pub fn add_task(&mut self, task: Task<'static, T, E>) -> Result<(), BuildError> {
+        let task_id = *task.id();
+        if self.tasks.contains_key(&task_id) {
+            return Err(BuildError::DuplicateTask(task_id));
+        }
+
+        self.tasks.insert(task_id, task);
+        if let Err(err) = resolve_groups(&self.tasks) {
+            self.tasks.remove(&task_id);
+            return Err(err.into());
+        }
+        match self.dry_run() {
+            Ok(blueprint) => {
+                self.blueprint = blueprint;
+                Ok(())
+            }
+            Err(err) => {
+                self.tasks.remove(&task_id);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Removes a task before execution, dropping it from the execution plan as well.
+    /// Tasks that depended on the removed task are no longer held back by it.
+    pub fn remove_task(&mut self, id: &TaskId) -> Option<Task<'static, T, E>> {
+        let (_, task) = self.tasks.remove(id)?;
+        for mut dependent in self.tasks.iter_mut() {
+            dependent.remove_dependency(id);
+        }
+        self.blueprint.remove_task(id);
+        Some(task)
+    }
+
+    pub fn get_task(&self, id: &TaskId) -> Option<impl Deref<Target = Task<'static, T, E>> + '_> {
+        self.tasks.get(id)
+    }
+
+    /// Folds the tasks of `other` into this executor and plans the combined tasks again.
+    /// Like with [`add_task`](Self::add_task), [`Dependency::Group`](crate::Dependency::Group)
+    /// dependencies are resolved to the tasks of the group in the combined executor.
+    ///
+    /// Fails with [`BuildError::DuplicateTask`] without modifying `self` if both
+    /// executors contain a task with the same id, or with the planning error if the
+    /// combined tasks can't be planned. The execution settings of `other` are discarded.
+    pub fn merge(&mut self, other: TaskExecutor<T, E>) -> Result<(), BuildError> {
+        if let Some(duplicate) = other
+            .tasks
+            .iter()
+            .find(|v| self.tasks.contains_key(v.key()))
+        {
+            return Err(BuildError::DuplicateTask(*duplicate.key()));
+        }
+
+        let merged: Vec<TaskId> = other.tasks.iter().map(|v| *v.key()).collect();
+        for (task_id, task) in other.tasks {
+            self.tasks.insert(task_id, task);
+        }
+        if let Err(err) = resolve_groups(&self.tasks) {
+            for task_id in &merged {
+                self.tasks.remove(task_id);
+            }
+            return Err(err.into());
+        }
+        match self.dry_run() {
+            Ok(blueprint) => {
+                self.blueprint = blueprint;
+                Ok(())
+            }
+            Err(err) => {
+                for task_id in &merged {
+                    self.tasks.remove(task_id);
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Returns a fresh plan of the tasks for inspection, without consuming the
+    /// executor or polling any future
+    pub fn dry_run(&self) -> Result<Blueprint, BlueprintError> {
+        Blueprint::from_nodes(&self.tasks, self.ignore_missing_dependencies)
+    }
+
+    pub fn contains(&self, id: &TaskId) -> bool {
+        self.tasks.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Same as [`len`](Self::len)
+    pub fn task_count(&self) -> usize {
+        self.len()
+    }
+
+    /// Number of steps the tasks will be executed in, planned without consuming the executor
+    pub fn expected_step_count(&self) -> Result<usize, BlueprintError> {
+        Ok(self.dry_run()?.step_count())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// When enabled, tasks whose dependencies failed (or were skipped themselves)
+    /// are not executed and are recorded as [`TaskOutcome::Skipped`] instead.
+    pub fn skip_on_failure(mut self, skip: bool) -> Self {
+        self.skip_on_failure = skip;
+        self
+    }
+
+    /// Caps the number of tasks running at the same time over the whole execution,
+    /// across step boundaries. Tasks wait for a free slot before starting.
+    pub fn with_max_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Stops the execution gracefully once `stop` is set: the running step completes,
+    /// but no further step starts and the result only holds the steps that ran.
+    /// With [`ExecutionMode::eager`] no further task starts.
+    pub fn with_stop_signal(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    fn stop_requested(&self) -> bool {
+        self.stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::SeqCst))
+    }
+
+    /// Returns a handle aborting the tasks of the step that is running when it is used
+    pub fn step_abort_handle(&self) -> StepAbortHandle {
+        self.abort_handle.clone()
+    }
+
+    /// Calls `hook` with the index of each step before its tasks are started
+    pub fn on_step_start(mut self, hook: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.hooks.step_start = Some(Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` when a task starts running, that is when its future is first polled.
+    /// With [`ExecutionMode::pseudo_async`] it may be called from the spawned task's thread.
+    pub fn on_task_start(mut self, hook: impl Fn(TaskId) + Send + Sync + 'static) -> Self {
+        self.hooks.task_start = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` with the result of every task that ran to completion,
+    /// successfully or not. Skipped, timed out and panicked tasks are not reported.
+    pub fn on_task_complete(
+        mut self,
+        hook: impl Fn(&TaskResult<T, E>) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.task_complete = Some(Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` with the index and the outcomes of each step once all of its tasks
+    /// finished, before the next step starts. Not called with [`ExecutionMode::eager`],
+    /// whose steps overlap.
+    pub fn on_step_complete(
+        mut self,
+        hook: impl Fn(usize, &[TaskOutcome<T, E>]) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.step_complete = Some(Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` with every progress report of the tasks created with
+    /// [`Task::from_fn_with_progress`], from whichever thread the task runs on.
+    /// Other tasks don't report progress.
+    pub fn on_progress(mut self, hook: impl Fn(TaskId, f32) + Send + Sync + 'static) -> Self {
+        self.hooks.progress = Some(Arc::new(hook));
+        self
+    }
+
     pub async fn execute(self) -> Result<ExecutionResult<T, E>, ExecutionError> {
-        let blueprint = &self.blueprint;
-        let mut execution_steps = vec![];
+        let mut result = ExecutionResult::default();
+        self.execute_into(&mut result).await?;
+        Ok(result)
+    }
+
+    /// Like [`execute`](Self::execute), but writes into `result`, reusing the
+    /// allocations of its steps. Whatever `result` held before is discarded;
+    /// if an error is returned, it holds the steps completed so far.
+    pub async fn execute_into(
+        self,
+        result: &mut ExecutionResult<T, E>,
+    ) -> Result<(), ExecutionError> {
+        let take_task = |task_id: &TaskId| self.tasks.remove(task_id).map(|(_, task)| task);
+        self.run(result, &take_task).await
+    }
+
+    /// Executes the tasks without consuming the executor, so that it can be executed again.
+    ///
+    /// Tasks created with [`Task::from_fn`] create new futures on every run. Other tasks
+    /// can only run once: after an execution consumed them, executing again fails with
+    /// [`ExecutionError::AlreadyExecuted`] before running anything.
+    pub async fn execute_ref(&self) -> Result<ExecutionResult<T, E>, ExecutionError> {
+        if self.consumed.load(Ordering::SeqCst) {
+            return Err(ExecutionError::AlreadyExecuted);
+        }
+        if self.tasks.iter().any(|v| !v.value().is_repeatable()) {
+            self.consumed.store(true, Ordering::SeqCst);
+        }
+
+        let mut result = ExecutionResult::default();
+        let take_task = |task_id: &TaskId| {
+            let instance = self
+                .tasks
+                .get(task_id)?
+                .instantiate(self.progress_sender(*task_id));
+            instance.or_else(|| self.tasks.remove(task_id).map(|(_, task)| task))
+        };
+        self.run(&mut result, &take_task).await?;
+        Ok(result)
+    }
+
+    /// Like [`execute`](Self::execute), but keeps the executor, so that the tasks that
+    /// didn't run because the execution stopped early remain in it, see
+    /// [`task_ids`](Self::task_ids) and [`ExecutionResult::unrun`]. The executor can't be
+    /// executed with [`execute_ref`](Self::execute_ref) afterwards.
+    pub async fn execute_keeping(&mut self) -> Result<ExecutionResult<T, E>, ExecutionError> {
+        self.consumed.store(true, Ordering::SeqCst);
+        let mut result = ExecutionResult::default();
+        let take_task = |task_id: &TaskId| self.tasks.remove(task_id).map(|(_, task)| task);
+        self.run(&mut result, &take_task).await?;
+        Ok(result)
+    }
+
+    /// Runs the plan, taking every task out of `take_task` right before starting it
+    async fn run(
+        &self,
+        result: &mut ExecutionResult<T, E>,
+        take_task: TakeTask<'_, T, E>,
+    ) -> Result<(), ExecutionError> {
+        let execution_steps = &mut result.steps;
+        execution_steps.truncate(self.blueprint.step_count());
+        for step in execution_steps.iter_mut() {
+            step.clear();
+        }
+        execution_steps.resize_with(self.blueprint.step_count(), Vec::new);
+        let step_timings = &mut result.step_timings;
+        step_timings.clear();
+
         let total_tasks = self.tasks.len();
-        let mut successful_tasks = 0;
-        let mut failed_tasks = 0;
+        let mut tally = Tally::default();
+        // Every task taken out is started, skipped or left out by its condition
+        let started: Mutex<HashSet<TaskId>> = Mutex::default();
+        let take_task = |task_id: &TaskId| {
+            let task = take_task(task_id)?;
+            started.lock().unwrap().insert(*task_id);
+            Some(task)
+        };
+        let take_task: TakeTask<'_, T, E> = &take_task;
+        let outcome = if self.mode.eager {
+            self.execute_eager(execution_steps, &mut tally, take_task)
+                .await
+        } else {
+            self.execute_steps(execution_steps, step_timings, &mut tally, take_task)
+                .await
+        };
+
+        let started = started.into_inner().unwrap();
+        if self.stop_requested() {
+            // Drop the steps after the last one a task was started in
+            let ran = started
+                .iter()
+                .filter_map(|task_id| self.blueprint.step_for_task(task_id))
+                .max()
+                .map_or(0, |step_index| step_index + 1);
+            result.steps.truncate(ran);
+        }
+        result.unrun = (0..self.blueprint.step_count())
+            .flat_map(|step_index| self.blueprint.tasks_at_step(step_index).unwrap())
+            .filter(|task_id| !started.contains(task_id))
+            .copied()
+            .collect();
+        result.total_tasks = total_tasks;
+        result.successful_tasks = tally.successful_tasks;
+        result.failed_tasks = tally.failed_tasks;
+        result.skipped_tasks = tally.skipped_tasks;
+        outcome
+    }
+
+    /// Executes the tasks step by step, each step waiting for the previous one to complete
+    async fn execute_steps(
+        &self,
+        execution_steps: &mut [Vec<TaskOutcome<T, E>>],
+        step_timings: &mut Vec<StepTiming>,
+        tally: &mut Tally,
+        take_task: TakeTask<'_, T, E>,
+    ) -> Result<(), ExecutionError> {
+        let started = Instant::now();
 
-        // Execute tasks step by step
-        for step_index in 0..blueprint.step_count() {
-            let task_ids = blueprint.tasks_at_step(step_index).unwrap();
+        for step_index in 0..self.blueprint.step_count() {
+            if self.stop_requested() {
+                break;
+            }
+            let task_ids = self.blueprint.tasks_at_step(step_index).unwrap();
+            if let Some(hook) = &self.hooks.step_start {
+                hook(step_index);
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(step = step_index, tasks = task_ids.len(), "step started");
+            let step_started = started.elapsed();
             let mut step_handles: Vec<StepHandle<T, E>> = vec![];
+            // Ids, names, groups and timings of the spawned tasks, in the same order as `step_handles`
+            let mut spawned = vec![];
+            let mut current_step_results = std::mem::take(&mut execution_steps[step_index]);
+
+            let mut step_tasks: Vec<_> = task_ids
+                .iter()
+                .filter_map(|task_id| Some((*task_id, take_task(task_id)?)))
+                .collect();
+            if self.mode.concurrency_limit.is_some() && !self.mode.sequential {
+                // Admit higher priority tasks first; the sort is stable so ties keep step order
+                step_tasks.sort_by_key(|(_, task)| Reverse(*task.priority()));
+            }
 
             // Spawn all tasks in this step concurrently
-            for task_id in task_ids {
-                let task_id = *task_id;
-                if let Some((_, task)) = self.tasks.remove(&task_id) {
-                    if let Some(spawn) = self.mode.execution_fn.as_ref() {
-                        let handle = spawn(task.into_task())
-                            .map(move |r| r.map(|result| TaskResult { task_id, result }));
-                        step_handles.push(Box::pin(handle));
-                    } else {
-                        step_handles.push(Box::pin(
-                            task.into_task()
-                                .map(move |result| Ok(TaskResult { task_id, result })),
-                        ));
-                    }
+            for (task_id, task) in step_tasks {
+                if !task.should_run() {
+                    current_step_results.push(tally.record_unmet_condition(task_id));
+                    continue;
+                }
+                if self.should_skip(&task, tally) {
+                    let outcome = TaskOutcome::Skipped(task_id);
+                    tally.record(&outcome);
+                    current_step_results.push(outcome);
+                    continue;
                 }
+
+                let labels = (task.name().clone(), task.group().clone());
+                let (timing, handle) = self.launch(task_id, task, execution_steps);
+                spawned.push((task_id, labels, timing));
+                step_handles.push(handle);
             }
 
             // Wait for all tasks in this step to complete
-            let step_results = futures::future::join_all(step_handles).await;
-            let mut current_step_results = vec![];
-
-            for join_result in step_results {
-                let task_result = join_result?;
-                if task_result.result.is_ok() {
-                    successful_tasks += 1;
-                } else {
-                    failed_tasks += 1;
+            let step_results = match self.mode.concurrency_limit {
+                Some(limit) => {
+                    futures::stream::iter(step_handles)
+                        .buffered(limit)
+                        .collect::<Vec<_>>()
+                        .await
+                }
+                None => futures::future::join_all(step_handles).await,
+            };
+            self.abort_handle.clear();
+            step_timings.push(StepTiming {
+                started: step_started,
+                finished: started.elapsed(),
+            });
+
+            for ((task_id, labels, timing), completion) in spawned.into_iter().zip(step_results) {
+                let outcome = self.complete(task_id, labels, &timing, completion, started);
+                tally.record(&outcome);
+                current_step_results.push(outcome);
+            }
+
+            // Report the step in a stable order, whatever order the tasks were started in
+            current_step_results.sort_by_key(TaskOutcome::task_id);
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                step = step_index,
+                tasks = current_step_results.len(),
+                "step completed"
+            );
+            if let Some(hook) = &self.hooks.step_complete {
+                hook(step_index, &current_step_results);
+            }
+            execution_steps[step_index] = current_step_results;
+        }
+
+        Ok(())
+    }
+
+    /// Executes every task as soon as all of its dependencies completed, without
+    /// waiting for the other tasks of the previous step
+    async fn execute_eager(
+        &self,
+        execution_steps: &mut [Vec<TaskOutcome<T, E>>],
+        tally: &mut Tally,
+        take_task: TakeTask<'_, T, E>,
+    ) -> Result<(), ExecutionError> {
+        let started = Instant::now();
+        let mut pending_dependencies: HashMap<TaskId, usize> = HashMap::new();
+        for dependent_id in self.blueprint.dependents.values().flatten() {
+            *pending_dependencies.entry(*dependent_id).or_default() += 1;
+        }
+        let mut ready: VecDeque<TaskId> = (0..self.blueprint.step_count())
+            .flat_map(|step_index| self.blueprint.tasks_at_step(step_index).unwrap())
+            .filter(|task_id| !pending_dependencies.contains_key(task_id))
+            .copied()
+            .collect();
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            if self.stop_requested() {
+                ready.clear();
+            }
+            while let Some(task_id) = ready.pop_front() {
+                let Some(task) = take_task(&task_id) else {
+                    continue;
+                };
+                if !task.should_run() {
+                    let outcome = tally.record_unmet_condition(task_id);
+                    self.release_dependents(&task_id, &mut pending_dependencies, &mut ready);
+                    self.push_outcome(execution_steps, outcome);
+                    continue;
+                }
+                if self.should_skip(&task, tally) {
+                    let outcome = TaskOutcome::Skipped(task_id);
+                    tally.record(&outcome);
+                    self.release_dependents(&task_id, &mut pending_dependencies, &mut ready);
+                    self.push_outcome(execution_steps, outcome);
+                    continue;
                 }
-                current_step_results.push(task_result);
+
+                let labels = (task.name().clone(), task.group().clone());
+                let (timing, handle) = self.launch(task_id, task, execution_steps);
+                running.push(handle.map(move |completion| (task_id, labels, timing, completion)));
             }
 
-            execution_steps.push(current_step_results);
+            let Some((task_id, labels, timing, completion)) = running.next().await else {
+                break;
+            };
+            let outcome = self.complete(task_id, labels, &timing, completion, started);
+            tally.record(&outcome);
+            self.release_dependents(&task_id, &mut pending_dependencies, &mut ready);
+            self.push_outcome(execution_steps, outcome);
+        }
+        self.abort_handle.clear();
+        for step in execution_steps.iter_mut() {
+            step.sort_by_key(TaskOutcome::task_id);
         }
 
-        Ok(ExecutionResult {
-            steps: execution_steps,
-            total_tasks,
-            successful_tasks,
-            failed_tasks,
-        })
+        Ok(())
+    }
+
+    /// Marks a finished task as no longer pending for its dependents, queueing
+    /// the ones that have nothing left to wait for
+    fn release_dependents(
+        &self,
+        task_id: &TaskId,
+        pending_dependencies: &mut HashMap<TaskId, usize>,
+        ready: &mut VecDeque<TaskId>,
+    ) {
+        for dependent_id in self.blueprint.dependents_of(task_id) {
+            if let Some(pending) = pending_dependencies.get_mut(dependent_id) {
+                *pending -= 1;
+                if *pending == 0 {
+                    ready.push_back(*dependent_id);
+                }
+            }
+        }
+    }
+
+    /// Stores the outcome under the blueprint step of its task
+    fn push_outcome(
+        &self,
+        execution_steps: &mut [Vec<TaskOutcome<T, E>>],
+        outcome: TaskOutcome<T, E>,
+    ) {
+        if let Some(step_index) = self.blueprint.step_for_task(&outcome.task_id()) {
+            execution_steps[step_index].push(outcome);
+        }
+    }
+
+    /// Whether the task has to be skipped because one of its dependencies was cancelled
+    /// or, with [`skip_on_failure`](Self::skip_on_failure), failed or was skipped
+    fn should_skip(&self, task: &Task<'static, T, E>, tally: &Tally) -> bool {
+        let held_back = if self.skip_on_failure {
+            &tally.unsatisfied
+        } else {
+            &tally.cancelled
+        };
+        task.dependencies().iter().any(|id| held_back.contains(&id))
+    }
+
+    /// Prepares a task for execution, handing it the outputs of the tasks that completed so far
+    fn launch(
+        &self,
+        task_id: TaskId,
+        task: Task<'static, T, E>,
+        execution_steps: &[Vec<TaskOutcome<T, E>>],
+    ) -> (Arc<Timing>, StepHandle<T, E>) {
+        // Indexed on the first lookup, as only tasks with inputs look outputs up
+        let outputs: OnceCell<HashMap<TaskId, &T>> = OnceCell::new();
+        let lookup = |id: &TaskId| {
+            outputs
+                .get_or_init(|| {
+                    execution_steps
+                        .iter()
+                        .flatten()
+                        .filter_map(TaskOutcome::as_completed)
+                        .filter_map(|task_result| {
+                            Some((task_result.task_id, task_result.result.as_ref().ok()?))
+                        })
+                        .collect()
+                })
+                .get(id)
+                .copied()
+        };
+
+        let timing = Arc::new(Timing::default());
+        let timeout = *task.timeout();
+        let spawner = self.mode.router.as_ref().map(|route| route(&task));
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "task",
+            task.id = %task_id,
+            task.name = task.name().as_deref().unwrap_or_default()
+        );
+        let task = task.into_task(
+            &lookup,
+            self.blocking_runner(),
+            self.progress_sender(task_id),
+        );
+        let task = Box::pin(Timing::timed(task, timing.clone()));
+        let task: UnitTask<'static, T, E> = match self.hooks.task_start.clone() {
+            Some(hook) => Box::pin(async move {
+                hook(task_id);
+                task.await
+            }),
+            None => task,
+        };
+        #[cfg(feature = "tracing")]
+        let task: UnitTask<'static, T, E> = Box::pin(tracing::Instrument::instrument(task, span));
+        let handles = self.abort_handle.clone();
+        let handle = self
+            .start_task(task_id, task, timeout, spawner)
+            .inspect(move |_| handles.forget(&task_id));
+        (timing, Box::pin(handle))
+    }
+
+    /// Turns the completion of a started task into its outcome, calling the completion hook
+    fn complete(
+        &self,
+        task_id: TaskId,
+        (name, group): (Option<String>, Option<String>),
+        timing: &Timing,
+        completion: Completion<T, E>,
+        started: Instant,
+    ) -> TaskOutcome<T, E> {
+        let result = match completion {
+            Completion::Finished(result) => result,
+            Completion::TimedOut => return TaskOutcome::TimedOut(task_id),
+            Completion::Panicked(message) => return TaskOutcome::Panicked(task_id, message),
+            Completion::Cancelled => return TaskOutcome::Cancelled(task_id),
+        };
+        let (duration, start_offset) = timing.span(started);
+        let task_result = TaskResult {
+            task_id,
+            name,
+            group,
+            result,
+            duration,
+            start_offset,
+        };
+        if let Some(hook) = &self.hooks.task_complete {
+            hook(&task_result);
+        }
+        TaskOutcome::Completed(task_result)
+    }
+
+    /// Forwards the progress reports of a task to the progress hook, if there is one
+    fn progress_sender(&self, task_id: TaskId) -> ProgressSender {
+        match self.hooks.progress.clone() {
+            Some(hook) => ProgressSender::new(move |progress| hook(task_id, progress)),
+            None => ProgressSender::default(),
+        }
+    }
+
+    /// Runs the synchronous work of blocking tasks
+    fn blocking_runner(&self) -> BlockingRunner<'static> {
+        #[cfg(feature = "rayon")]
+        if self.mode.parallel {
+            return Arc::new(rayon::spawn);
+        }
+        if self.mode.spawn_blocking {
+            // The job reports its result and panics through its own channel
+            return Arc::new(|job| {
+                tokio::task::spawn_blocking(job);
+            });
+        }
+
+        Arc::new(|job| job())
+    }
+
+    /// Starts a task according to the execution mode, enforcing its timeout
+    /// and turning panics into a task-level failure.
+    ///
+    /// The timeout starts when the returned handle is first polled, so that tasks
+    /// queued behind others by [`ExecutionMode::bounded`] don't time out while waiting.
+    fn start_task(
+        &self,
+        task_id: TaskId,
+        task: UnitTask<'static, T, E>,
+        timeout: Option<Duration>,
+        spawner: Option<Spawner<T, E>>,
+    ) -> StepHandle<T, E> {
+        let semaphore = self.concurrency.clone();
+        let handle = match (spawner, &self.mode.execution_fn) {
+            (Some(spawner), _) => spawner(Box::pin(Self::admitted(task, semaphore))),
+            (None, Some(execution_fn)) => execution_fn(Box::pin(Self::admitted(task, semaphore))),
+            (None, None) => {
+                let (task, abort_handle) = futures::future::abortable(task);
+                self.abort_handle
+                    .register(task_id, AbortHandle::Polled(abort_handle));
+                let task = AssertUnwindSafe(task).catch_unwind().map(|r| match r {
+                    Ok(Ok(result)) => Completion::Finished(result),
+                    Ok(Err(Aborted)) => Completion::Cancelled,
+                    Err(payload) => Completion::Panicked(panic_message(payload)),
+                });
+                return Box::pin(async move {
+                    // Waiting for a free slot doesn't count towards the timeout either
+                    let _permit = Self::acquire(semaphore).await;
+                    match timeout {
+                        Some(timeout) => {
+                            let deadline = Instant::now() + timeout;
+                            match tokio::time::timeout(timeout, task).await {
+                                // A blocking closure running in place can't be interrupted,
+                                // it can only be caught overrunning once it returns
+                                Ok(completion) if Instant::now() <= deadline => completion,
+                                _ => Completion::TimedOut,
+                            }
+                        }
+                        None => task.await,
+                    }
+                });
+            }
+        };
+        let abort_handle = handle.abort_handle();
+        self.abort_handle
+            .register(task_id, AbortHandle::Spawned(abort_handle.clone()));
+        // A spawned task only fails to join when it panicked or was aborted
+        let handle = handle.map(|join_result| match join_result {
+            Ok(result) => Completion::Finished(result),
+            Err(err) => match err.try_into_panic() {
+                Ok(payload) => Completion::Panicked(panic_message(payload)),
+                Err(_) => Completion::Cancelled,
+            },
+        });
+        match timeout {
+            Some(timeout) => Box::pin(async move {
+                tokio::time::timeout(timeout, handle)
+                    .await
+                    .unwrap_or_else(|_| {
+                        // Abort the spawned task rather than leaving it running detached
+                        abort_handle.abort();
+                        Completion::TimedOut
+                    })
+            }),
+            None => Box::pin(handle),
+        }
+    }
+
+    /// Runs the task once the concurrency cap, if any, leaves room for it
+    async fn admitted(
+        task: UnitTask<'static, T, E>,
+        semaphore: Option<Arc<Semaphore>>,
+    ) -> Result<T, E> {
+        let _permit = Self::acquire(semaphore).await;
+        task.await
+    }
+
+    /// Waits for a free slot under the concurrency cap set with
+    /// [`with_max_concurrency`](Self::with_max_concurrency)
+    async fn acquire(semaphore: Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+        match semaphore {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("The semaphore is never closed"),
+            ),
+            None => None,
+        }
+    }
+
+    /// Executes all tasks and returns the values of the successful ones
+    pub async fn execute_and_collect_results(self) -> Result<Vec<T>, ExecutionError> {
+        Ok(self.execute().await?.into_successful_values())
     }
 }
 
@@ -79,7 +858,11 @@ mod tests {
     use super::*;
     use crate::exec::builder::TaskExecutorBuilder;
     use crate::{Dependency, Task};
+    use std::collections::HashMap;
     use std::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_new_executor() {
@@ -136,10 +919,11 @@ mod tests {
         assert_eq!(result.total_tasks, 1);
         assert_eq!(result.successful_tasks, 1);
         assert_eq!(result.failed_tasks, 0);
+        assert_eq!(result.first_error(), None);
         assert_eq!(result.steps.len(), 1);
         assert_eq!(result.steps[0].len(), 1);
-        assert!(result.steps[0][0].result.is_ok());
-        assert_eq!(result.steps[0][0].result.as_ref().unwrap(), &42);
+        assert!(result.steps[0][0].result().unwrap().is_ok());
+        assert_eq!(result.steps[0][0].result().unwrap().as_ref().unwrap(), &42);
         assert!(result.all_successful());
     }
 
@@ -157,8 +941,15 @@ mod tests {
         assert_eq!(result.failed_tasks, 1);
         assert_eq!(result.steps.len(), 1);
         assert_eq!(result.steps[0].len(), 1);
-        assert!(result.steps[0][0].result.is_err());
-        assert_eq!(result.steps[0][0].result.as_ref().unwrap_err(), &"error");
+        assert!(result.steps[0][0].result().unwrap().is_err());
+        assert_eq!(
+            result.steps[0][0].result().unwrap().as_ref().unwrap_err(),
+            &"error"
+        );
+        assert_eq!(
+            result.first_error(),
+            Some((&result.steps[0][0].task_id(), &"error"))
+        );
         assert!(!result.all_successful());
     }
 
@@ -230,6 +1021,1470 @@ mod tests {
         assert_eq!(result.total_tasks, 1);
         assert_eq!(result.successful_tasks, 1);
         assert_eq!(result.failed_tasks, 0);
-        assert_eq!(result.steps[0][0].result.as_ref().unwrap(), &100);
+        assert_eq!(result.steps[0][0].result().unwrap().as_ref().unwrap(), &100);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_with_inputs() {
+        let task1 = Task::new_independent(future::ready(Ok::<i32, ()>(2)));
+        let task2 = Task::new_independent(future::ready(Ok::<i32, ()>(3)));
+        let id1 = *task1.id();
+        let id2 = *task2.id();
+        let sum = Task::with_inputs(
+            |inputs: HashMap<TaskId, i32>| async move { Ok(inputs.values().sum()) },
+            [id1, id2],
+        );
+        let sum_id = *sum.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2).insert(sum);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.steps.len(), 2);
+        assert_eq!(result.steps[1][0].task_id(), sum_id);
+        assert_eq!(result.steps[1][0].result().unwrap().as_ref().unwrap(), &5);
+    }
+
+    #[tokio::test]
+    async fn test_skip_on_failure() {
+        let task_a = Task::new_independent(future::ready(Err::<i32, &str>("fail")));
+        let id_a = *task_a.id();
+        let task_b = Task::new(future::ready(Ok::<i32, &str>(2)), [id_a]);
+        let id_b = *task_b.id();
+        let task_c = Task::new(future::ready(Ok::<i32, &str>(3)), [id_b]);
+        let id_c = *task_c.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder
+            .insert(task_a)
+            .insert(task_b)
+            .insert(task_c);
+        let executor = executor_builder.build().unwrap().skip_on_failure(true);
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.total_tasks, 3);
+        assert_eq!(result.successful_tasks, 0);
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.skipped_tasks, 2);
+        assert_eq!(result.steps.len(), 3);
+        assert!(result.steps[0][0].result().unwrap().is_err());
+        assert!(matches!(result.steps[1][0], TaskOutcome::Skipped(id) if id == id_b));
+        assert!(matches!(result.steps[2][0], TaskOutcome::Skipped(id) if id == id_c));
+        assert_eq!(
+            result.skipped_results().collect::<Vec<_>>(),
+            vec![id_b, id_c]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmet_condition() {
+        for mode in [ExecutionMode::true_async(), ExecutionMode::eager()] {
+            let gated = Task::new_independent(future::ready(Ok::<i32, &str>(1))).when(|| false);
+            let gated_id = *gated.id();
+            let dependent = Task::new(future::ready(Ok::<i32, &str>(2)), [gated_id]);
+            let dependent_id = *dependent.id();
+
+            let executor_builder = TaskExecutorBuilder::new(mode);
+            executor_builder.insert(gated).insert(dependent);
+            let executor = executor_builder.build().unwrap().skip_on_failure(true);
+
+            let result = executor.execute().await.unwrap();
+
+            assert_eq!(result.skipped_results().collect::<Vec<_>>(), vec![gated_id]);
+            assert_eq!(result.successful_tasks, 1);
+            assert_eq!(result.steps[1][0].task_id(), dependent_id);
+            assert_eq!(result.steps[1][0].result(), Some(&Ok(2)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_step_timings() {
+        let sleeping = |millis| async move {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Ok::<u64, ()>(millis)
+        };
+        let fast = Task::new_independent(sleeping(10));
+        let slow = Task::new_independent(sleeping(100));
+        let dependent = Task::new(sleeping(10), [*fast.id(), *slow.id()]);
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(fast).insert(slow).insert(dependent);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.step_timings.len(), 2);
+        let first_step = result.step_timings[0];
+        let slowest = result.steps[0]
+            .iter()
+            .filter_map(TaskOutcome::as_completed)
+            .max_by_key(|task_result| task_result.duration)
+            .unwrap();
+        assert!(first_step.duration() >= Duration::from_millis(100));
+        assert!(first_step.finished >= slowest.start_offset + slowest.duration);
+        assert!(result.step_timings[1].started >= first_step.finished);
+        assert!(result.gap_between_steps(0).is_some());
+        assert_eq!(result.gap_between_steps(1), None);
+    }
+
+    #[test]
+    fn test_remove_task() {
+        let task1 = Task::new_independent(future::ready(Ok::<(), ()>(())))
+            .with_cost(Duration::from_millis(10));
+        let task2 = Task::new_independent(future::ready(Ok::<(), ()>(())));
+        let id1 = *task1.id();
+        let id2 = *task2.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2);
+        let mut executor = executor_builder.build().unwrap();
+
+        assert_eq!(executor.len(), 2);
+        assert!(executor.contains(&id1));
+        assert_eq!(*executor.get_task(&id2).unwrap().id(), id2);
+
+        let removed = executor.remove_task(&id1).unwrap();
+        assert_eq!(*removed.id(), id1);
+        assert!(executor.remove_task(&id1).is_none());
+        assert!(!executor.contains(&id1));
+        assert!(executor.contains(&id2));
+        assert_eq!(executor.len(), 1);
+        assert_eq!(executor.blueprint.tasks_at_step(0).unwrap(), &[id2]);
+        assert_eq!(executor.blueprint.step_for_task(&id1), None);
+        assert_eq!(executor.blueprint.depth_of(&id1), None);
+        assert!(executor.blueprint.costs.is_empty());
+
+        executor.remove_task(&id2);
+        assert!(executor.is_empty());
+        assert_eq!(executor.blueprint.step_count(), 0);
+        assert_eq!(executor.blueprint.max_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_remove_task_with_dependent() {
+        let task1 = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let id1 = *task1.id();
+        let task2 = Task::new(future::ready(Ok::<i32, ()>(2)), id1);
+        let id2 = *task2.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2);
+        let mut executor = executor_builder.build().unwrap();
+
+        executor.remove_task(&id1).unwrap();
+        assert!(
+            executor
+                .get_task(&id2)
+                .unwrap()
+                .dependencies()
+                .iter()
+                .next()
+                .is_none()
+        );
+        assert_eq!(
+            executor.dry_run().unwrap().tasks_at_step(0).unwrap(),
+            &[id2]
+        );
+        assert_eq!(executor.expected_step_count().unwrap(), 1);
+        executor
+            .add_task(Task::new(future::ready(Ok(3)), id2))
+            .unwrap();
+
+        let result = executor.execute().await.unwrap();
+        assert_eq!(result.successful_tasks, 2);
+    }
+
+    fn two_task_executor() -> TaskExecutor<i32, ()> {
+        let task1 = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let task2 = Task::new(future::ready(Ok::<i32, ()>(2)), [*task1.id()]);
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2);
+        executor_builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_task() {
+        let mut executor = two_task_executor();
+        let last_id = executor.blueprint.tasks_at_step(1).unwrap()[0];
+
+        let task = Task::new(future::ready(Ok::<i32, ()>(3)), last_id);
+        let task_id = *task.id();
+        executor.add_task(task).unwrap();
+
+        let missing = Task::new(future::ready(Ok::<i32, ()>(4)), TaskId::generate());
+        assert!(matches!(
+            executor.add_task(missing),
+            Err(BuildError::BlueprintError(
+                BlueprintError::MissingDependency(..)
+            ))
+        ));
+        assert_eq!(executor.len(), 3);
+        assert_eq!(executor.blueprint.step_for_task(&task_id), Some(2));
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.steps.len(), 3);
+        assert_eq!(result.into_successful_values(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let executor = two_task_executor();
+
+        let blueprint = executor.dry_run().unwrap();
+
+        assert_eq!(blueprint.step_count(), 2);
+        assert_eq!(executor.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_result_step_index() {
+        let executor = two_task_executor().skip_on_failure(true);
+        let first_id = executor.blueprint.tasks_at_step(0).unwrap()[0];
+        let second_id = executor.blueprint.tasks_at_step(1).unwrap()[0];
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.step_index(&first_id), Some(0));
+        assert_eq!(result.step_index(&second_id), Some(1));
+        assert_eq!(result.step_index(&TaskId::generate()), None);
+    }
+
+    #[test]
+    fn test_counts() {
+        let executor = two_task_executor();
+
+        assert_eq!(executor.task_count(), 2);
+        assert!(!executor.is_empty());
+        assert_eq!(executor.expected_step_count().unwrap(), 2);
+
+        let empty = TaskExecutorBuilder::<(), ()>::new(ExecutionMode::true_async())
+            .build()
+            .unwrap();
+        assert_eq!(empty.task_count(), 0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.expected_step_count().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_merge_executors() {
+        let mut executor = two_task_executor();
+        executor.merge(two_task_executor()).unwrap();
+
+        assert_eq!(executor.len(), 4);
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.total_tasks, 4);
+        assert_eq!(result.successful_tasks, 4);
+        assert_eq!(result.steps.len(), 2);
+        assert_eq!(result.steps[0].len(), 2);
+        assert_eq!(result.steps[1].len(), 2);
+    }
+
+    #[test]
+    fn test_merge_duplicate_task() {
+        let task = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let task_id = *task.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task);
+        let mut executor = executor_builder.build().unwrap();
+
+        // A different task under the same id
+        let other_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        other_builder
+            .insert(Task::new_independent(future::ready(Ok::<i32, ()>(2))).with_id(task_id));
+        let other = other_builder.build().unwrap();
+
+        let err = executor.merge(other).unwrap_err();
+        assert!(matches!(err, BuildError::DuplicateTask(id) if id == task_id));
+        assert_eq!(executor.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_resolves_groups() {
+        let member = Task::new_independent(future::ready(Err::<i32, ()>(()))).with_group("ingest");
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(member);
+        let mut executor = executor_builder.build().unwrap().skip_on_failure(true);
+
+        // Inserted past the builder, which would reject the group it doesn't contain
+        let dependent = Task::new(
+            future::ready(Ok(2)),
+            Dependency::Group("ingest".to_string()),
+        );
+        let dependent_id = *dependent.id();
+        let other = TaskExecutor::default();
+        other.tasks.insert(dependent_id, dependent);
+        executor.merge(other).unwrap();
+
+        assert!(
+            !executor
+                .get_task(&dependent_id)
+                .unwrap()
+                .dependencies()
+                .has_groups()
+        );
+        let result = executor.execute().await.unwrap();
+        assert_eq!(result.failed_tasks, 1);
+        assert!(matches!(result.steps[1][0], TaskOutcome::Skipped(id) if id == dependent_id));
+    }
+
+    #[test]
+    fn test_merge_replans() {
+        let mut executor = two_task_executor();
+        let first_id = executor.blueprint.tasks_at_step(0).unwrap()[0];
+        let last_id = executor.blueprint.tasks_at_step(1).unwrap()[0];
+
+        let exclusive = Task::new_independent(future::ready(Ok::<i32, ()>(3)))
+            .with_exclusive(true)
+            .with_cost(Duration::from_secs(5));
+        let exclusive_id = *exclusive.id();
+        let other_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        other_builder.insert(exclusive);
+        executor.merge(other_builder.build().unwrap()).unwrap();
+
+        let blueprint = &executor.blueprint;
+        assert_eq!(blueprint.width_per_step(), [1, 1, 1]);
+        assert_eq!(blueprint.tasks_at_step(1).unwrap(), &[exclusive_id]);
+        assert_eq!(blueprint.depth_of(&exclusive_id), Some(0));
+        assert_eq!(blueprint.depth_of(&last_id), Some(1));
+        assert_eq!(blueprint.critical_path(), [exclusive_id]);
+        assert_eq!(blueprint.step_for_task(&first_id), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_collect_results() {
+        let task1 = Task::new_independent(future::ready(Ok::<i32, &str>(1)));
+        let task2 = Task::new(future::ready(Ok::<i32, &str>(2)), [*task1.id()]);
+        let task3 = Task::new(future::ready(Ok::<i32, &str>(3)), [*task2.id()]);
+        let task4 = Task::new_independent(future::ready(Err::<i32, &str>("fail")));
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder
+            .insert(task1)
+            .insert(task2)
+            .insert(task3)
+            .insert(task4);
+        let executor = executor_builder.build().unwrap();
+
+        let mut values = executor.execute_and_collect_results().await.unwrap();
+        values.sort();
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_groups_in_result() {
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        for (group, result) in [
+            ("ingest", Ok(1)),
+            ("ingest", Err("bad input")),
+            ("ingest", Ok(2)),
+            ("load", Err("unreachable")),
+        ] {
+            executor_builder.insert(Task::new_independent(future::ready(result)).with_group(group));
+        }
+        executor_builder.insert(Task::new_independent(future::ready(Ok(3))));
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let groups = result.by_group();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["ingest"], (2, 1));
+        assert_eq!(groups["load"], (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_task_name_in_result() {
+        let named =
+            Task::new_independent(future::ready(Err::<i32, &str>("boom"))).with_name("fetch-users");
+        let unnamed = Task::new_independent(future::ready(Ok::<i32, &str>(1)));
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::pseudo_async(tokio::spawn));
+        executor_builder.insert(named).insert(unnamed);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let failed: Vec<_> = result.failed_results().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].name.as_deref(), Some("fetch-users"));
+        assert_eq!(failed[0].to_string(), "Task 'fetch-users'");
+
+        let succeeded: Vec<_> = result.successful_results().collect();
+        assert_eq!(succeeded[0].name, None);
+        assert_eq!(
+            succeeded[0].to_string(),
+            format!("Task {}", succeeded[0].task_id)
+        );
+    }
+
+    #[test]
+    fn test_task_metadata() {
+        let mut task = Task::new_independent(future::ready(Ok::<(), ()>(())))
+            .with_metadata("team", "billing")
+            .with_metadata("cost", "low");
+        assert_eq!(task.insert_metadata("cost", "high").as_deref(), Some("low"));
+        let task_id = *task.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task);
+        let executor = executor_builder.build().unwrap();
+
+        let task = executor.get_task(&task_id).unwrap();
+        assert_eq!(task.metadata().len(), 2);
+        assert_eq!(task.metadata()["team"], "billing");
+        assert_eq!(task.metadata()["cost"], "high");
+    }
+
+    #[tokio::test]
+    async fn test_bounded_priority_order() {
+        let order = Arc::new(Mutex::new(vec![]));
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::bounded(1));
+        for priority in [1, 5, -3] {
+            let order = order.clone();
+            let task = Task::new_independent(async move {
+                order.lock().unwrap().push(priority);
+                Ok::<i32, ()>(priority)
+            })
+            .with_priority(priority);
+            executor_builder.insert(task);
+        }
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 3);
+        assert_eq!(*order.lock().unwrap(), vec![5, 1, -3]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_starts_when_admitted() {
+        let slow = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<i32, ()>(1)
+        })
+        .with_priority(1);
+        let queued = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok::<i32, ()>(2)
+        })
+        .with_timeout(Duration::from_millis(150));
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::bounded(1));
+        executor_builder.insert(slow).insert(queued);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 2);
+        assert!(!result.steps[0].iter().any(TaskOutcome::is_timed_out));
+    }
+
+    #[tokio::test]
+    async fn test_task_timeout() {
+        for mode in [
+            ExecutionMode::true_async(),
+            ExecutionMode::pseudo_async(tokio::spawn),
+        ] {
+            let slow = Task::new_independent(async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok::<i32, ()>(1)
+            })
+            .with_timeout(Duration::from_millis(20));
+            let slow_id = *slow.id();
+            let fast = Task::new_independent(future::ready(Ok::<i32, ()>(2)))
+                .with_timeout(Duration::from_secs(10));
+
+            let executor_builder = TaskExecutorBuilder::new(mode);
+            executor_builder.insert(slow).insert(fast);
+            let executor = executor_builder.build().unwrap();
+
+            let start = Instant::now();
+            let result = executor.execute().await.unwrap();
+
+            assert!(start.elapsed() < Duration::from_secs(1));
+            assert_eq!(result.successful_tasks, 1);
+            assert_eq!(result.failed_tasks, 1);
+            let timed_out: Vec<_> = result.steps[0]
+                .iter()
+                .filter(|o| o.is_timed_out())
+                .collect();
+            assert_eq!(timed_out.len(), 1);
+            assert_eq!(timed_out[0].task_id(), slow_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_panic_is_captured() {
+        for mode in [
+            ExecutionMode::true_async(),
+            ExecutionMode::pseudo_async(tokio::spawn),
+        ] {
+            let panicking = Task::new_independent(async {
+                if true {
+                    panic!("task exploded");
+                }
+                Ok::<i32, ()>(1)
+            });
+            let panicking_id = *panicking.id();
+            let healthy = Task::new_independent(future::ready(Ok::<i32, ()>(2)));
+
+            let executor_builder = TaskExecutorBuilder::new(mode);
+            executor_builder.insert(panicking).insert(healthy);
+            let executor = executor_builder.build().unwrap();
+
+            let result = executor.execute().await.unwrap();
+
+            assert_eq!(result.successful_tasks, 1);
+            assert_eq!(result.failed_tasks, 1);
+            let panicked = result.steps[0]
+                .iter()
+                .find_map(|outcome| match outcome {
+                    TaskOutcome::Panicked(task_id, message) => Some((*task_id, message.as_str())),
+                    _ => None,
+                })
+                .unwrap();
+            assert_eq!(panicked, (panicking_id, "task exploded"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_mode() {
+        let blocking = Task::from_blocking(
+            || {
+                std::thread::sleep(Duration::from_millis(200));
+                Ok::<i32, ()>(1)
+            },
+            [],
+        );
+        let blocking_id = *blocking.id();
+        let ticking = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok::<i32, ()>(2)
+        });
+        let ticking_id = *ticking.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::blocking());
+        executor_builder.insert(blocking).insert(ticking);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 2);
+        let finished_at = |task_id| {
+            let task_result = result
+                .successful_results()
+                .find(|task_result| task_result.task_id == task_id)
+                .unwrap();
+            task_result.start_offset + task_result.duration
+        };
+        // The async task was not held up by the sleeping thread
+        assert!(finished_at(ticking_id) < Duration::from_millis(150));
+        assert!(finished_at(blocking_id) >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_blocking_task_starts_when_polled() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::blocking());
+        for i in 0..4 {
+            let (running, peak) = (running.clone(), peak.clone());
+            executor_builder.insert(Task::from_blocking(
+                move || {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now_running, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok::<i32, ()>(i)
+                },
+                [],
+            ));
+        }
+        let executor = executor_builder.build().unwrap().with_max_concurrency(1);
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 4);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_task_in_place_timing() {
+        let blocking = || {
+            Task::from_blocking(
+                || {
+                    std::thread::sleep(Duration::from_millis(100));
+                    Ok::<i32, ()>(1)
+                },
+                [],
+            )
+        };
+        let timed_out = blocking().with_timeout(Duration::from_millis(20));
+        let timed_out_id = *timed_out.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(blocking()).insert(timed_out);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let completed = result.successful_results().next().unwrap();
+        assert!(completed.duration >= Duration::from_millis(100));
+        assert_eq!(result.failed_tasks, 1);
+        assert!(
+            result.steps[0]
+                .iter()
+                .any(|outcome| outcome.is_timed_out() && outcome.task_id() == timed_out_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_routed_mode() {
+        let runtime = |name: &str| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name(name)
+                .build()
+                .unwrap()
+        };
+        let io_runtime = runtime("io-pool");
+        let cpu_runtime = runtime("cpu-pool");
+        let spawner = |runtime: &tokio::runtime::Runtime| -> Spawner<String, ()> {
+            let handle = runtime.handle().clone();
+            Arc::new(move |task| handle.spawn(task))
+        };
+        let (io_spawner, cpu_spawner) = (spawner(&io_runtime), spawner(&cpu_runtime));
+
+        let thread_name = || async {
+            Ok::<_, ()>(
+                std::thread::current()
+                    .name()
+                    .unwrap_or_default()
+                    .to_string(),
+            )
+        };
+        let io_task = Task::new_independent(thread_name()).with_group("io");
+        let io_id = *io_task.id();
+        let cpu_task = Task::new_independent(thread_name()).with_group("cpu");
+        let cpu_id = *cpu_task.id();
+
+        let mode = ExecutionMode::routed(move |task| match task.group().as_deref() {
+            Some("io") => io_spawner.clone(),
+            _ => cpu_spawner.clone(),
+        });
+        let executor_builder = TaskExecutorBuilder::new(mode);
+        executor_builder.insert(io_task).insert(cpu_task);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+        let outputs: HashMap<_, _> = result.into_result().unwrap().into_iter().collect();
+
+        assert_eq!(outputs[&io_id], "io-pool");
+        assert_eq!(outputs[&cpu_id], "cpu-pool");
+        io_runtime.shutdown_background();
+        cpu_runtime.shutdown_background();
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_tracing_spans() {
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct SpanCounter {
+            task_spans: Arc<AtomicUsize>,
+            next_id: AtomicUsize,
+        }
+
+        impl Subscriber for SpanCounter {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                if span.metadata().name() == "task" {
+                    self.task_spans.fetch_add(1, Ordering::SeqCst);
+                }
+                Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) as u64 + 1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let task1 = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let task2 = Task::new(future::ready(Ok::<i32, ()>(2)), *task1.id()).with_name("second");
+        let task3 = Task::new_independent(future::ready(Ok::<i32, ()>(3)));
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2).insert(task3);
+        let executor = executor_builder.build().unwrap();
+
+        let subscriber = SpanCounter::default();
+        let task_spans = subscriber.task_spans.clone();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        executor.execute().await.unwrap();
+
+        assert_eq!(task_spans.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[tokio::test]
+    async fn test_parallel_mode() {
+        let task1 = Task::from_blocking(
+            || {
+                std::thread::sleep(Duration::from_millis(100));
+                Ok::<i32, ()>(1)
+            },
+            [],
+        );
+        let task2 = Task::from_blocking(
+            || {
+                std::thread::sleep(Duration::from_millis(100));
+                Ok::<i32, ()>(2)
+            },
+            [],
+        );
+        let sum = Task::with_inputs(
+            |inputs: HashMap<TaskId, i32>| async move { Ok(inputs.values().sum()) },
+            [*task1.id(), *task2.id()],
+        );
+        let sum_id = *sum.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::parallel());
+        executor_builder.insert(task1).insert(task2).insert(sum);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 3);
+        // How much the blocking tasks overlap depends on the size of the rayon pool,
+        // only their order relative to their dependent is certain
+        let blocking_finished = result.steps[0]
+            .iter()
+            .filter_map(TaskOutcome::as_completed)
+            .inspect(|task_result| assert!(task_result.duration >= Duration::from_millis(100)))
+            .map(|task_result| task_result.start_offset + task_result.duration)
+            .max()
+            .unwrap();
+        let sum_result = result.steps[1][0].as_completed().unwrap();
+        assert!(sum_result.start_offset >= blocking_finished);
+        assert_eq!(sum_result.task_id, sum_id);
+        assert_eq!(sum_result.result, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_mode() {
+        let order = Arc::new(Mutex::new(vec![]));
+        let sleeping_task = |label: &'static str, priority: i32| {
+            let order = order.clone();
+            Task::new_independent(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                order.lock().unwrap().push(label);
+                Ok::<(), ()>(())
+            })
+            .with_priority(priority)
+        };
+        let tasks = [
+            sleeping_task("a", 0),
+            sleeping_task("b", 10),
+            sleeping_task("c", 5),
+        ];
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::sequential());
+        for task in tasks {
+            executor_builder.insert(task);
+        }
+        let executor = executor_builder.build().unwrap();
+        let blueprint_order = executor.blueprint.tasks_at_step(0).unwrap().to_vec();
+
+        let start = Instant::now();
+        let result = executor.execute().await.unwrap();
+
+        // Every task waited for the previous one
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(result.steps.len(), 1);
+        let executed: Vec<_> = result.steps[0].iter().map(TaskOutcome::task_id).collect();
+        assert_eq!(executed, blueprint_order);
+        assert_eq!(order.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_task_timing() {
+        let sleeping_task = || {
+            Task::new_independent(async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok::<(), ()>(())
+            })
+        };
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::bounded(1));
+        executor_builder
+            .insert(sleeping_task())
+            .insert(sleeping_task());
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let mut timings: Vec<_> = result
+            .successful_results()
+            .map(|task_result| (task_result.start_offset, task_result.duration))
+            .collect();
+        timings.sort();
+        for (_, duration) in &timings {
+            // Waiting for the other task to finish is not part of the duration
+            assert!(*duration >= Duration::from_millis(50));
+            assert!(*duration < Duration::from_millis(90));
+        }
+        assert!(timings[1].0 >= Duration::from_millis(50));
+        assert!(result.total_wall_time() >= Duration::from_millis(100));
+        assert!(result.slowest_task().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_hooks() {
+        let task1 = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let task2 = Task::new_independent(future::ready(Ok::<i32, ()>(2)));
+        let task3 = Task::new(future::ready(Ok::<i32, ()>(3)), [*task1.id()]);
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task1).insert(task2).insert(task3);
+        let started = Arc::new(Mutex::new(vec![]));
+        let completed = Arc::new(Mutex::new(vec![]));
+        let steps = Arc::new(Mutex::new(vec![]));
+        let completed_steps = Arc::new(Mutex::new(vec![]));
+
+        let executor = executor_builder
+            .build()
+            .unwrap()
+            .on_step_start({
+                let steps = steps.clone();
+                move |step| steps.lock().unwrap().push(step)
+            })
+            .on_step_complete({
+                let completed_steps = completed_steps.clone();
+                move |step, outcomes| completed_steps.lock().unwrap().push((step, outcomes.len()))
+            })
+            .on_task_start({
+                let started = started.clone();
+                move |task_id| started.lock().unwrap().push(task_id)
+            })
+            .on_task_complete({
+                let completed = completed.clone();
+                move |task_result| completed.lock().unwrap().push(task_result.task_id)
+            });
+        let mut task_ids = executor.task_ids();
+        task_ids.sort();
+
+        executor.execute().await.unwrap();
+
+        let mut started = started.lock().unwrap().clone();
+        started.sort();
+        let mut completed = completed.lock().unwrap().clone();
+        completed.sort();
+        assert_eq!(started, task_ids);
+        assert_eq!(completed, task_ids);
+        assert_eq!(*steps.lock().unwrap(), [0, 1]);
+        assert_eq!(*completed_steps.lock().unwrap(), [(0, 2), (1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_abort_step() {
+        let sleeping_task = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<i32, ()>(1)
+        });
+        let task_id = *sleeping_task.id();
+        let dependent = Task::new(future::ready(Ok::<i32, ()>(2)), task_id);
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::pseudo_async(tokio::spawn));
+        executor_builder.insert(sleeping_task).insert(dependent);
+        let executor = executor_builder.build().unwrap().skip_on_failure(true);
+        let abort_handle = executor.step_abort_handle();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            abort_handle.abort_step();
+        });
+
+        let start = Instant::now();
+        let result = executor.execute().await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(matches!(result.steps[0][0], TaskOutcome::Cancelled(id) if id == task_id));
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.skipped_tasks, 1);
+    }
+
+    async fn assert_cancels_task(mode: ExecutionMode<u64, ()>) {
+        let sleeping = |millis| async move {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Ok::<u64, ()>(millis)
+        };
+        let stuck = Task::new_independent(sleeping(10_000));
+        let stuck_id = *stuck.id();
+        let healthy = Task::new_independent(sleeping(100));
+        let healthy_id = *healthy.id();
+        let dependent = Task::new(sleeping(0), stuck_id);
+
+        let executor_builder = TaskExecutorBuilder::new(mode);
+        executor_builder
+            .insert(stuck)
+            .insert(healthy)
+            .insert(dependent);
+        let executor = executor_builder.build().unwrap();
+        let abort_handle = executor.step_abort_handle();
+        assert!(!abort_handle.cancel_task(stuck_id));
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert!(abort_handle.cancel_task(stuck_id));
+        });
+
+        let start = Instant::now();
+        let result = executor.execute().await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let outcome = |task_id| {
+            result
+                .steps
+                .iter()
+                .flatten()
+                .find(|outcome| outcome.task_id() == task_id)
+                .unwrap()
+        };
+        assert!(matches!(outcome(stuck_id), TaskOutcome::Cancelled(_)));
+        assert_eq!(outcome(healthy_id).result(), Some(&Ok(100)));
+        assert_eq!(result.skipped_tasks, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_task() {
+        assert_cancels_task(ExecutionMode::pseudo_async(tokio::spawn)).await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_polled_task() {
+        assert_cancels_task(ExecutionMode::true_async()).await;
+        assert_cancels_task(ExecutionMode::eager()).await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_finished_task() {
+        let quick = Task::new_independent(future::ready(Ok::<u64, ()>(0)));
+        let quick_id = *quick.id();
+        let slow = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<u64, ()>(200)
+        });
+        let slow_id = *slow.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::eager());
+        executor_builder.insert(quick).insert(slow);
+        let executor = executor_builder.build().unwrap();
+        let abort_handle = executor.step_abort_handle();
+        let canceller = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let cancelled = abort_handle.cancel_task(quick_id);
+            abort_handle.abort_step();
+            cancelled
+        });
+
+        let result = executor.execute().await.unwrap();
+
+        assert!(!canceller.await.unwrap());
+        assert_eq!(result.successful_tasks, 1);
+        assert_eq!(result.failed_tasks, 1);
+        let outcomes: Vec<_> = result.steps.iter().flatten().collect();
+        assert!(
+            outcomes
+                .iter()
+                .any(|o| matches!(o, TaskOutcome::Cancelled(id) if *id == slow_id))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_into_reuses_buffer() {
+        let mut buffer = ExecutionResult::default();
+        two_task_executor().execute_into(&mut buffer).await.unwrap();
+        let capacities: Vec<_> = buffer.steps.iter().map(Vec::capacity).collect();
+        let first_step = buffer.steps[0].as_ptr();
+
+        two_task_executor().execute_into(&mut buffer).await.unwrap();
+
+        assert_eq!(buffer.total_tasks, 2);
+        assert_eq!(buffer.successful_tasks, 2);
+        assert_eq!(buffer.steps.len(), 2);
+        assert_eq!(
+            buffer.steps.iter().map(Vec::capacity).collect::<Vec<_>>(),
+            capacities
+        );
+        assert_eq!(buffer.steps[0].as_ptr(), first_step);
+        assert_eq!(buffer.into_successful_values(), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_weak_dependency() {
+        let failing = Task::new_independent(future::ready(Err::<i32, ()>(())));
+        let failing_id = *failing.id();
+        let after_failing = Task::new(future::ready(Ok(1)), Dependency::Weak(failing_id));
+        let after_failing_id = *after_failing.id();
+        let after_absent = Task::new(future::ready(Ok(2)), Dependency::Weak(TaskId::generate()));
+        let after_absent_id = *after_absent.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder
+            .insert(failing)
+            .insert(after_failing)
+            .insert(after_absent);
+        let executor = executor_builder.build().unwrap().skip_on_failure(true);
+        assert_eq!(executor.blueprint.step_for_task(&after_failing_id), Some(1));
+        assert_eq!(executor.blueprint.step_for_task(&after_absent_id), Some(0));
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.skipped_tasks, 0);
+        assert_eq!(result.successful_tasks, 2);
+    }
+
+    /// A fast branch followed by a slow task, next to a slow branch
+    fn uneven_branches(mode: ExecutionMode<i32, ()>) -> TaskExecutor<i32, ()> {
+        let sleeping_task = |millis: u64, dependency: Dependency| {
+            Task::new(
+                async move {
+                    tokio::time::sleep(Duration::from_millis(millis)).await;
+                    Ok::<i32, ()>(1)
+                },
+                dependency,
+            )
+        };
+        let root = sleeping_task(0, Dependency::None);
+        let fast = sleeping_task(20, Dependency::from(*root.id()));
+        let slow = sleeping_task(100, Dependency::from(*root.id()));
+        let after_fast = sleeping_task(80, Dependency::from(*fast.id()));
+
+        let executor_builder = TaskExecutorBuilder::new(mode);
+        executor_builder
+            .insert(root)
+            .insert(fast)
+            .insert(slow)
+            .insert(after_fast);
+        executor_builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_eager_mode() {
+        let start = Instant::now();
+        let result = uneven_branches(ExecutionMode::true_async())
+            .execute()
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(180));
+        assert_eq!(result.successful_tasks, 4);
+
+        let start = Instant::now();
+        let result = uneven_branches(ExecutionMode::eager())
+            .execute()
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert_eq!(result.successful_tasks, 4);
+        assert_eq!(
+            result.steps.iter().map(Vec::len).collect::<Vec<_>>(),
+            [1, 2, 1]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eager_mode_skips_dependents() {
+        let failing = Task::new_independent(future::ready(Err::<i32, ()>(())));
+        let dependent = Task::new(future::ready(Ok(1)), *failing.id());
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::eager());
+        executor_builder.insert(failing).insert(dependent);
+
+        let result = executor_builder
+            .build()
+            .unwrap()
+            .skip_on_failure(true)
+            .execute()
+            .await
+            .unwrap();
+
+        assert_eq!(result.total_tasks, 2);
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.skipped_tasks, 1);
+        assert!(result.steps[1][0].is_skipped());
+    }
+
+    #[tokio::test]
+    async fn test_step_results_sorted_by_id() {
+        for mode in [
+            ExecutionMode::bounded(1),
+            ExecutionMode::true_async(),
+            ExecutionMode::eager(),
+        ] {
+            let executor_builder = TaskExecutorBuilder::new(mode);
+            // Later tasks finish first and, under a cap, higher priorities start first
+            for index in 0..5 {
+                let task = Task::new_independent(async move {
+                    tokio::time::sleep(Duration::from_millis(5 * (5 - index))).await;
+                    Ok::<u64, ()>(index)
+                })
+                .with_priority(index as i32);
+                executor_builder.insert(task);
+            }
+            let executor = executor_builder.build().unwrap();
+            let blueprint = executor.dry_run().unwrap();
+
+            let result = executor.execute().await.unwrap();
+
+            let ids: Vec<_> = result.steps[0].iter().map(TaskOutcome::task_id).collect();
+            assert_eq!(ids, blueprint.tasks_at_step(0).unwrap());
+            assert!(ids.is_sorted());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_barrier() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let work = |delay: u64, dependencies: Dependency| {
+            let finished = finished.clone();
+            Task::new(
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    finished.fetch_add(1, Ordering::SeqCst);
+                    Ok::<(), ()>(())
+                },
+                dependencies,
+            )
+        };
+        // Two parallel groups: a chain of two tasks and a single slow task
+        let first = work(5, Dependency::None);
+        let second = work(5, Dependency::from(&first));
+        let slow = work(30, Dependency::None);
+        let barrier = Task::barrier(Dependency::on_tasks([&second, &slow]));
+        let downstream = Task::new(
+            {
+                let finished = finished.clone();
+                async move { Ok(finished.load(Ordering::SeqCst)) }
+            },
+            &barrier,
+        );
+        let downstream_id = *downstream.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::eager());
+        for task in [first, second, slow, barrier] {
+            executor_builder.insert(task.map(|()| 0));
+        }
+        executor_builder.insert(downstream);
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let downstream_result = result
+            .successes()
+            .find(|(task_id, _)| *task_id == downstream_id)
+            .unwrap();
+        assert_eq!(*downstream_result.1, 3);
+    }
+
+    #[tokio::test]
+    async fn test_completed_all_steps() {
+        let chain = |stop: Arc<AtomicBool>| {
+            let first = Task::new_independent(async move {
+                // Stops the execution while its first step is running
+                stop.store(true, Ordering::SeqCst);
+                Ok::<i32, ()>(1)
+            });
+            let second = Task::new(future::ready(Ok::<i32, ()>(2)), *first.id());
+            let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+            executor_builder.insert(first).insert(second);
+            executor_builder.build().unwrap()
+        };
+
+        let executor = chain(Arc::new(AtomicBool::new(false)));
+        let blueprint = executor.dry_run().unwrap();
+        let result = executor.execute().await.unwrap();
+        assert!(result.completed_all_steps(&blueprint));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let executor = chain(stop.clone()).with_stop_signal(stop);
+        let blueprint = executor.dry_run().unwrap();
+        let result = executor.execute().await.unwrap();
+        assert_eq!(result.steps.len(), 1);
+        assert!(!result.completed_all_steps(&blueprint));
+    }
+
+    #[tokio::test]
+    async fn test_execute_ref() {
+        let runs = Arc::new(Mutex::new(0));
+        let counting_task = Task::from_fn(
+            {
+                let runs = runs.clone();
+                move || {
+                    let runs = runs.clone();
+                    async move {
+                        *runs.lock().unwrap() += 1;
+                        Ok::<i32, ()>(1)
+                    }
+                }
+            },
+            [],
+        );
+        let dependent = Task::from_fn(|| future::ready(Ok(2)), *counting_task.id());
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(counting_task).insert(dependent);
+        let executor = executor_builder.build().unwrap();
+
+        for _ in 0..3 {
+            let result = executor.execute_ref().await.unwrap();
+            assert_eq!(result.into_successful_values(), [1, 2]);
+        }
+        assert_eq!(*runs.lock().unwrap(), 3);
+        assert_eq!(executor.len(), 2);
+        assert_eq!(executor.execute().await.unwrap().successful_tasks, 2);
+
+        let single_use = two_task_executor();
+        let result = single_use.execute_ref().await.unwrap();
+        assert_eq!(result.into_successful_values(), [1, 2]);
+        assert!(matches!(
+            single_use.execute_ref().await,
+            Err(ExecutionError::AlreadyExecuted)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency() {
+        for mode in [ExecutionMode::true_async(), ExecutionMode::eager()] {
+            let running = Arc::new(AtomicUsize::new(0));
+            let peak = Arc::new(AtomicUsize::new(0));
+            let executor_builder = TaskExecutorBuilder::new(mode);
+            let mut previous = None;
+            for index in 0..6 {
+                let running = running.clone();
+                let peak = peak.clone();
+                // Chain every other task so that tasks of different steps can overlap
+                let dependencies = match (index % 2, previous) {
+                    (1, Some(id)) => Dependency::from(id),
+                    _ => Dependency::None,
+                };
+                let task = Task::new(
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<usize, ()>(index)
+                    },
+                    dependencies,
+                );
+                previous = Some(*task.id());
+                executor_builder.insert(task);
+            }
+            let executor = executor_builder.build().unwrap().with_max_concurrency(2);
+
+            let result = executor.execute().await.unwrap();
+            assert_eq!(result.successful_tasks, 6);
+            assert_eq!(peak.load(Ordering::SeqCst), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_signal() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let first = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let first_id = *first.id();
+        let second = Task::new(future::ready(Ok::<i32, ()>(2)), first_id);
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(first).insert(second);
+        let executor = executor_builder
+            .build()
+            .unwrap()
+            .with_stop_signal(stop.clone())
+            .on_step_complete(move |_, _| stop.store(true, Ordering::SeqCst));
+        let blueprint = executor.dry_run().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        assert!(!result.completed_all_steps(&blueprint));
+        assert_eq!(result.steps.len(), 1);
+        assert_eq!(result.steps[0][0].task_id(), first_id);
+        assert_eq!(result.successful_tasks, 1);
+        assert_eq!(result.total_tasks, 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_keeping() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let first = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let first_id = *first.id();
+        let second = Task::new(future::ready(Ok::<i32, ()>(2)), first_id);
+        let second_id = *second.id();
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(first).insert(second);
+        let mut executor = executor_builder
+            .build()
+            .unwrap()
+            .with_stop_signal(stop.clone())
+            .on_step_complete(move |_, _| stop.store(true, Ordering::SeqCst));
+
+        let result = executor.execute_keeping().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 1);
+        assert_eq!(result.unrun, vec![second_id]);
+        assert_eq!(executor.task_ids(), vec![second_id]);
+        assert!(matches!(
+            executor.execute_ref().await,
+            Err(ExecutionError::AlreadyExecuted)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_eager_stop_within_step() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let quick = Task::new_independent(future::ready(Ok::<i32, ()>(1)));
+        let slow = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<i32, ()>(2)
+        });
+        // Both in the second step, the first one stopping the execution before the other starts
+        let stopping = Task::new(
+            {
+                let stop = stop.clone();
+                async move {
+                    stop.store(true, Ordering::SeqCst);
+                    Ok::<i32, ()>(3)
+                }
+            },
+            *quick.id(),
+        );
+        let stopping_id = *stopping.id();
+        let unstarted = Task::new(future::ready(Ok::<i32, ()>(4)), *slow.id());
+        let unstarted_id = *unstarted.id();
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::eager());
+        executor_builder
+            .insert(quick)
+            .insert(slow)
+            .insert(stopping)
+            .insert(unstarted);
+        let executor = executor_builder.build().unwrap().with_stop_signal(stop);
+
+        let result = executor.execute().await.unwrap();
+
+        assert_eq!(result.unrun, vec![unstarted_id]);
+        assert_eq!(result.successful_tasks, 3);
+        assert_eq!(result.steps.len(), 2);
+        assert_eq!(result.steps[1].len(), 1);
+        assert_eq!(result.steps[1][0].task_id(), stopping_id);
+    }
+
+    #[tokio::test]
+    async fn test_execute_keeping_partial_results() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let first = Task::new_independent({
+            let stop = stop.clone();
+            async move {
+                // Stops the execution while its first step is running
+                stop.store(true, Ordering::SeqCst);
+                Ok::<i32, ()>(1)
+            }
+        });
+        let sibling = Task::new_independent(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<i32, ()>(10)
+        });
+        let second = Task::new(future::ready(Ok::<i32, ()>(2)), *first.id());
+        let third = Task::new(future::ready(Ok::<i32, ()>(3)), *second.id());
+        let (second_id, third_id) = (*second.id(), *third.id());
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder
+            .insert(first)
+            .insert(sibling)
+            .insert(second)
+            .insert(third);
+        let mut executor = executor_builder.build().unwrap().with_stop_signal(stop);
+
+        let result = executor.execute_keeping().await.unwrap();
+
+        assert_eq!(result.unrun, vec![second_id, third_id]);
+        let mut kept = executor.task_ids();
+        kept.sort();
+        let mut expected = vec![second_id, third_id];
+        expected.sort();
+        assert_eq!(kept, expected);
+        assert!(executor.get_task(&third_id).is_some());
+        // The step that was running when the stop was requested completed
+        assert_eq!(result.steps.len(), 1);
+        let mut values = result.into_successful_values();
+        values.sort();
+        assert_eq!(values, [1, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_any_outputs() {
+        let fetch = Task::new_independent(future::ready(Ok::<_, ()>("page".to_string())));
+        let fetch_id = *fetch.id();
+        let count = Task::new(future::ready(Ok::<_, ()>(42usize)), fetch_id);
+        let count_id = *count.id();
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder
+            .insert(fetch.into_any())
+            .insert(count.into_any());
+        let executor = executor_builder.build().unwrap();
+
+        let result = executor.execute().await.unwrap();
+
+        let output = |task_id| {
+            result
+                .successful_results()
+                .find(|task_result| task_result.task_id == task_id)
+                .unwrap()
+        };
+        assert_eq!(output(fetch_id).downcast_ref::<String>().unwrap(), "page");
+        assert_eq!(output(count_id).downcast_ref::<usize>(), Some(&42));
+        assert_eq!(output(count_id).downcast_ref::<String>(), None);
+        let counted = result
+            .into_iter()
+            .find(|task_result| task_result.task_id == count_id)
+            .unwrap();
+        assert_eq!(counted.downcast::<usize>(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_progress() {
+        let task = Task::from_fn_with_progress(
+            |progress| async move {
+                progress.send(0.5);
+                progress.send(1.0);
+                Ok::<(), ()>(())
+            },
+            [],
+        );
+        let task_id = *task.id();
+        let silent = Task::from_fn(|| future::ready(Ok::<(), ()>(())), []);
+
+        let executor_builder = TaskExecutorBuilder::new(ExecutionMode::true_async());
+        executor_builder.insert(task).insert(silent);
+        let events = Arc::new(Mutex::new(vec![]));
+        let executor = executor_builder.build().unwrap().on_progress({
+            let events = events.clone();
+            move |task_id, progress| events.lock().unwrap().push((task_id, progress))
+        });
+
+        executor.execute_ref().await.unwrap();
+        executor.execute_ref().await.unwrap();
+
+        let reported = vec![(task_id, 0.5), (task_id, 1.0)];
+        assert_eq!(
+            *events.lock().unwrap(),
+            [reported.clone(), reported].concat()
+        );
     }
 }
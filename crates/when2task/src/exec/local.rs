@@ -0,0 +1,191 @@
+use super::executor::{Timing, panic_message};
+use crate::blueprint::Blueprint;
+use crate::result::{ExecutionResult, StepTiming, TaskOutcome, TaskResult};
+use crate::{BuildError, ExecutionError, LocalTask, TaskId};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::LocalSet;
+
+/// Executes tasks whose futures are not `Send`, spawning them on a [`LocalSet`].
+///
+/// Everything runs on the thread awaiting [`execute`](Self::execute): the tasks of
+/// a step are still interleaved at their await points, but never run in parallel,
+/// so a task that blocks holds up its whole step. Prefer a
+/// [`TaskExecutor`](crate::TaskExecutor) whenever the tasks are `Send`.
+///
+/// It runs every step to completion and supports none of the options of a
+/// [`TaskExecutor`](crate::TaskExecutor):
+///
+/// - a failed or panicked task never skips its dependents, as if
+///   [`skip_on_failure`](crate::TaskExecutor::skip_on_failure) was off, so
+///   [`ExecutionResult::skipped_tasks`] is always `0`
+/// - [`LocalTask`] has no timeout, priority, group, condition or inputs
+/// - there are no lifecycle or progress hooks, stop signal, abort handle or
+///   concurrency cap
+/// - every planned task runs, so [`ExecutionResult::unrun`] is always empty
+pub struct LocalTaskExecutor<T, E> {
+    tasks: DashMap<TaskId, LocalTask<T, E>>,
+    blueprint: Blueprint,
+}
+
+impl<T: 'static, E: 'static> LocalTaskExecutor<T, E> {
+    pub fn new(tasks: impl IntoIterator<Item = LocalTask<T, E>>) -> Result<Self, BuildError> {
+        let tasks: DashMap<_, _> = tasks.into_iter().map(|task| (*task.id(), task)).collect();
+        let blueprint = Blueprint::from_nodes(&tasks, false)?;
+
+        Ok(Self { tasks, blueprint })
+    }
+
+    pub fn blueprint(&self) -> &Blueprint {
+        &self.blueprint
+    }
+
+    pub async fn execute(self) -> Result<ExecutionResult<T, E>, ExecutionError> {
+        LocalSet::new().run_until(self.execute_steps()).await
+    }
+
+    async fn execute_steps(self) -> Result<ExecutionResult<T, E>, ExecutionError> {
+        let started = Instant::now();
+        let mut execution_steps = vec![];
+        let total_tasks = self.tasks.len();
+        let mut successful_tasks = 0;
+        let mut failed_tasks = 0;
+        let mut step_timings = vec![];
+
+        for step_index in 0..self.blueprint.step_count() {
+            let task_ids = self.blueprint.tasks_at_step(step_index).unwrap();
+            let step_started = started.elapsed();
+            // Spawn the whole step before awaiting any of it
+            let handles: Vec<_> = task_ids
+                .iter()
+                .filter_map(|task_id| self.tasks.remove(task_id))
+                .map(|(task_id, task)| {
+                    let name = task.name().clone();
+                    let timing = Arc::new(Timing::default());
+                    let task = Timing::timed(task.into_task(), timing.clone());
+                    (task_id, name, timing, tokio::task::spawn_local(task))
+                })
+                .collect();
+
+            let mut current_step_results = vec![];
+            for (task_id, name, timing, handle) in handles {
+                let result = match handle.await {
+                    Ok(result) => result,
+                    Err(err) => {
+                        failed_tasks += 1;
+                        current_step_results.push(match err.try_into_panic() {
+                            Ok(payload) => TaskOutcome::Panicked(task_id, panic_message(payload)),
+                            Err(_) => TaskOutcome::Cancelled(task_id),
+                        });
+                        continue;
+                    }
+                };
+                if result.is_ok() {
+                    successful_tasks += 1;
+                } else {
+                    failed_tasks += 1;
+                }
+                let (duration, start_offset) = timing.span(started);
+                current_step_results.push(TaskOutcome::Completed(TaskResult {
+                    task_id,
+                    name,
+                    group: None,
+                    result,
+                    duration,
+                    start_offset,
+                }));
+            }
+
+            step_timings.push(StepTiming {
+                started: step_started,
+                finished: started.elapsed(),
+            });
+            execution_steps.push(current_step_results);
+        }
+
+        Ok(ExecutionResult {
+            steps: execution_steps,
+            total_tasks,
+            successful_tasks,
+            failed_tasks,
+            skipped_tasks: 0,
+            unrun: vec![],
+            step_timings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[tokio::test]
+    async fn test_execute_non_send_tasks() {
+        let log = Rc::new(RefCell::new(vec![]));
+
+        let first_log = log.clone();
+        let first = LocalTask::new_independent(async move {
+            tokio::task::yield_now().await;
+            first_log.borrow_mut().push("first");
+            Ok::<_, ()>(1)
+        });
+        let second_log = log.clone();
+        let second = LocalTask::new(
+            async move {
+                second_log.borrow_mut().push("second");
+                Ok(2)
+            },
+            *first.id(),
+        )
+        .with_name("second");
+
+        let executor = LocalTaskExecutor::new([first, second]).unwrap();
+        assert_eq!(executor.blueprint().step_count(), 2);
+
+        let result = executor.execute().await.unwrap();
+
+        assert!(result.all_successful());
+        assert_eq!(*log.borrow(), ["first", "second"]);
+        assert_eq!(result.into_successful_values(), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_failure_does_not_skip_dependents() {
+        let failing = LocalTask::new_independent(async { Err::<i32, _>("boom") });
+        let dependent = LocalTask::new(async { Ok(2) }, *failing.id());
+        let dependent_id = *dependent.id();
+
+        let result = LocalTaskExecutor::new([failing, dependent])
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.successful_tasks, 1);
+        assert_eq!(result.skipped_tasks, 0);
+        assert_eq!(result.steps[1][0].task_id(), dependent_id);
+        assert_eq!(result.steps[1][0].result(), Some(&Ok(2)));
+    }
+
+    #[tokio::test]
+    async fn test_local_task_panic() {
+        let task = LocalTask::new_independent(async { panic!("boom") as Result<(), ()> });
+        let task_id = *task.id();
+
+        let result = LocalTaskExecutor::new([task])
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+
+        assert_eq!(result.failed_tasks, 1);
+        assert!(matches!(
+            &result.steps[0][0],
+            TaskOutcome::Panicked(id, message) if *id == task_id && message == "boom"
+        ));
+    }
+}
@@ -1,9 +1,13 @@
+mod abort;
 mod builder;
 mod errors;
 mod executor;
+mod local;
 mod mode;
 
+pub use abort::*;
 pub use builder::*;
 pub use errors::*;
 pub use executor::*;
+pub use local::*;
 pub use mode::*;
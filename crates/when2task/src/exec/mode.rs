@@ -1,5 +1,7 @@
+use crate::{Task, UnitTask};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use tokio::task::JoinHandle;
 
 type ExecutionFn<T, E> = Box<
@@ -8,19 +10,122 @@ type ExecutionFn<T, E> = Box<
         + 'static,
 >;
 
+/// Spawns a task on a runtime or thread pool of its choice, see [`ExecutionMode::routed`]
+pub type Spawner<T, E> =
+    Arc<dyn Fn(UnitTask<'static, T, E>) -> JoinHandle<Result<T, E>> + Send + Sync + 'static>;
+
+type RouteFn<T, E> = Box<dyn Fn(&Task<'static, T, E>) -> Spawner<T, E> + Send + 'static>;
+
 use derive_getters::Getters;
 
+/// The constructor an [`ExecutionMode`] was created with, see [`ExecutionMode::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeKind {
+    TrueAsync,
+    Bounded,
+    Sequential,
+    Eager,
+    PseudoAsync,
+    Routed,
+    Blocking,
+    #[cfg(feature = "rayon")]
+    Parallel,
+}
+
+/// Decides how the tasks of a step are run.
+///
+/// Every mode accepts both future-based tasks and blocking tasks created with
+/// [`Task::from_blocking`](crate::Task::from_blocking). Futures are always polled by the
+/// executor (or the spawner of [`pseudo_async`](Self::pseudo_async)), while blocking tasks
+/// only leave the executing thread with [`blocking`](Self::blocking) and
+/// [`parallel`](Self::parallel).
 #[derive(Getters)]
 pub struct ExecutionMode<T, E> {
     pub(crate) execution_fn: Option<ExecutionFn<T, E>>,
+    pub(crate) concurrency_limit: Option<usize>,
+    #[getter(skip)]
+    pub(crate) sequential: bool,
+    #[getter(skip)]
+    pub(crate) eager: bool,
+    #[getter(skip)]
+    pub(crate) spawn_blocking: bool,
+    #[getter(skip)]
+    pub(crate) router: Option<RouteFn<T, E>>,
+    #[cfg(feature = "rayon")]
+    #[getter(skip)]
+    pub(crate) parallel: bool,
 }
 
 impl<T, E> ExecutionMode<T, E> {
+    /// Which kind of mode this is, e.g. to log how an executor is configured
+    pub fn kind(&self) -> ModeKind {
+        #[cfg(feature = "rayon")]
+        if self.parallel {
+            return ModeKind::Parallel;
+        }
+        if self.sequential {
+            ModeKind::Sequential
+        } else if self.concurrency_limit.is_some() {
+            ModeKind::Bounded
+        } else if self.eager {
+            ModeKind::Eager
+        } else if self.execution_fn.is_some() {
+            ModeKind::PseudoAsync
+        } else if self.router.is_some() {
+            ModeKind::Routed
+        } else if self.spawn_blocking {
+            ModeKind::Blocking
+        } else {
+            ModeKind::TrueAsync
+        }
+    }
+
     /// Everything function is executed truly asynchronously
     /// For example, if a step has tasks A, B and C, we execute
     /// each of them asynchronously.
     pub fn true_async() -> Self {
-        Self { execution_fn: None }
+        Self {
+            execution_fn: None,
+            concurrency_limit: None,
+            sequential: false,
+            eager: false,
+            spawn_blocking: false,
+            router: None,
+            #[cfg(feature = "rayon")]
+            parallel: false,
+        }
+    }
+
+    /// Like [`true_async`](Self::true_async), but at most `limit` tasks of a step
+    /// run at the same time. Tasks are admitted by descending priority, ties
+    /// being broken by the step order. A limit of `0` is treated as `1`.
+    pub fn bounded(limit: usize) -> Self {
+        Self {
+            concurrency_limit: Some(limit.max(1)),
+            ..Self::true_async()
+        }
+    }
+
+    /// Tasks are awaited one at a time in blueprint order, ignoring priorities,
+    /// which makes runs reproducible when debugging. The result keeps the
+    /// step grouping of the blueprint.
+    pub fn sequential() -> Self {
+        Self {
+            concurrency_limit: Some(1),
+            sequential: true,
+            ..Self::true_async()
+        }
+    }
+
+    /// Tasks are started as soon as all of their own dependencies completed,
+    /// rather than when the whole previous step completed. This keeps a slow
+    /// task from holding up the tasks that don't depend on it. The result is
+    /// still grouped by blueprint step, and step start hooks are not called.
+    pub fn eager() -> Self {
+        Self {
+            eager: true,
+            ..Self::true_async()
+        }
     }
 
     /// All the individual tasks in a step are executed in parallel,
@@ -37,10 +142,70 @@ impl<T, E> ExecutionMode<T, E> {
     {
         Self {
             execution_fn: Some(Box::new(execution_fn)),
+            ..Self::true_async()
+        }
+    }
+
+    /// Like [`pseudo_async`](Self::pseudo_async), but the spawner is picked for each task
+    /// by `classifier`, so that e.g. I/O-bound and CPU-bound tasks can be sent to
+    /// different runtimes.
+    pub fn routed<F>(classifier: F) -> Self
+    where
+        F: Fn(&Task<'static, T, E>) -> Spawner<T, E> + Send + 'static,
+    {
+        Self {
+            router: Some(Box::new(classifier)),
+            ..Self::true_async()
+        }
+    }
+
+    /// Blocking tasks run on tokio's blocking thread pool through
+    /// [`spawn_blocking`](tokio::task::spawn_blocking), so that they don't stall the
+    /// async tasks. Future-based tasks are polled like in [`true_async`](Self::true_async).
+    /// Must be executed within a tokio runtime.
+    pub fn blocking() -> Self {
+        Self {
+            spawn_blocking: true,
+            ..Self::true_async()
+        }
+    }
+
+    /// Blocking tasks of a step run in parallel on the global rayon thread pool,
+    /// which suits CPU-bound work better than the async runtime. Future-based
+    /// tasks are still polled concurrently, like in [`true_async`](Self::true_async).
+    #[cfg(feature = "rayon")]
+    pub fn parallel() -> Self {
+        Self {
+            parallel: true,
+            ..Self::true_async()
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
 
-    /*pub fn parallel() -> Self {
-        todo!()
-    }*/
+    #[test]
+    fn test_kind() {
+        type Mode = ExecutionMode<(), ()>;
+
+        assert_eq!(Mode::true_async().kind(), ModeKind::TrueAsync);
+        assert_eq!(Mode::bounded(2).kind(), ModeKind::Bounded);
+        assert_eq!(Mode::sequential().kind(), ModeKind::Sequential);
+        assert_eq!(Mode::eager().kind(), ModeKind::Eager);
+        assert_eq!(
+            Mode::pseudo_async(tokio::spawn).kind(),
+            ModeKind::PseudoAsync
+        );
+        let spawner: Spawner<(), ()> = Arc::new(tokio::spawn);
+        assert_eq!(
+            Mode::routed(move |_| spawner.clone()).kind(),
+            ModeKind::Routed
+        );
+        assert_eq!(Mode::blocking().kind(), ModeKind::Blocking);
+        #[cfg(feature = "rayon")]
+        assert_eq!(Mode::parallel().kind(), ModeKind::Parallel);
+    }
 }
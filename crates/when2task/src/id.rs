@@ -1,10 +1,101 @@
-use derive_more::Display;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, PartialOrd, Ord)]
+/// Namespace of the ids derived from names, the UUIDv5 of the repository URL
+/// in the URL namespace
+const NAME_NAMESPACE: Uuid = Uuid::from_u128(0x42758e073bab5057a0ffba33b1a1e5d3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct TaskId(u128);
 
 impl TaskId {
     pub fn generate() -> Self {
-        TaskId(uuid::Uuid::new_v4().as_u128())
+        TaskId(Uuid::new_v4().as_u128())
+    }
+
+    /// Derives the id from a name, as a UUIDv5 over a fixed namespace, so that a
+    /// name maps to the same id across runs and processes. Tasks can then depend on
+    /// a task before it is built, by name.
+    ///
+    /// The same name always gives the same id: two tasks created from the same name
+    /// collide and are rejected as duplicates by the executor. Different names giving
+    /// the same id is as unlikely as with generated ids.
+    pub fn from_name(name: &str) -> Self {
+        Self::from_uuid(Uuid::new_v5(&NAME_NAMESPACE, name.as_bytes()))
+    }
+
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        TaskId(uuid.as_u128())
+    }
+
+    pub fn as_uuid(&self) -> Uuid {
+        Uuid::from_u128(self.0)
+    }
+
+    /// Builds an id from its raw value, the inverse of [`TaskId::to_u128`]
+    pub fn from_u128(value: u128) -> Self {
+        TaskId(value)
+    }
+
+    /// Returns the raw value of the id
+    pub fn to_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+/// Formats the id as a hyphenated UUID, like `67e55044-10b1-426f-9247-bb680e5fe0c8`
+impl Display for TaskId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.as_uuid(), f)
+    }
+}
+
+/// Serialized as a string, most JSON consumers can't represent a `u128` number exactly
+#[cfg(feature = "serde")]
+impl serde::Serialize for TaskId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_as_uuid() {
+        let task_id = TaskId::generate();
+
+        assert_eq!(
+            task_id.to_string(),
+            Uuid::from_u128(task_id.to_u128()).to_string()
+        );
+        assert_eq!(
+            TaskId(0x67e5504410b1426f9247bb680e5fe0c8).to_string(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            TaskId::from_name("fetch-users"),
+            TaskId::from_name("fetch-users")
+        );
+        assert_ne!(
+            TaskId::from_name("fetch-users"),
+            TaskId::from_name("fetch-orders")
+        );
+    }
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let uuid = Uuid::new_v4();
+        let task_id = TaskId::from_uuid(uuid);
+
+        assert_eq!(task_id.as_uuid(), uuid);
+        assert_eq!(task_id.to_u128(), uuid.as_u128());
+        assert_eq!(TaskId::from_uuid(task_id.as_uuid()), task_id);
+        assert_eq!(TaskId::from_u128(task_id.to_u128()), task_id);
     }
 }
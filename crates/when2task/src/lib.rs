@@ -6,8 +6,10 @@ mod id;
 mod result;
 mod task;
 
+pub use blueprint::*;
 pub use dependency::*;
 pub use errors::*;
 pub use exec::*;
 pub use id::*;
+pub use result::*;
 pub use task::*;
@@ -1,39 +1,849 @@
-use crate::TaskId;
+use crate::blueprint::Blueprint;
+use crate::{AnyOutput, TaskId};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::iter::{FilterMap, Flatten};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct TaskResult<T, E> {
     pub task_id: TaskId,
+    pub name: Option<String>,
+    /// Group the task was put in with [`Task::with_group`](crate::Task::with_group)
+    pub group: Option<String>,
     pub result: Result<T, E>,
+    /// Time the task's future took, from its first poll to its completion
+    pub duration: Duration,
+    /// Time between the start of the execution and the first poll of the task
+    pub start_offset: Duration,
+}
+
+/// When a step started and finished, relative to the start of the execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepTiming {
+    /// From right before the tasks of the step were started
+    pub started: Duration,
+    /// To once all of them completed
+    pub finished: Duration,
+}
+
+impl StepTiming {
+    pub fn duration(&self) -> Duration {
+        self.finished.saturating_sub(self.started)
+    }
+}
+
+impl<E> TaskResult<AnyOutput, E> {
+    /// The output of a task made with [`Task::into_any`](crate::Task::into_any), if the
+    /// task succeeded and its output is a `U`
+    pub fn downcast_ref<U: Any>(&self) -> Option<&U> {
+        self.result.as_ref().ok()?.downcast_ref()
+    }
+
+    /// Like [`downcast_ref`](Self::downcast_ref), taking the output
+    pub fn downcast<U: Any>(self) -> Option<U> {
+        let value = self.result.ok()?.downcast().ok()?;
+        Some(*value)
+    }
+}
+
+/// Identifies the task by its name if it has one, and by its id otherwise
+impl<T, E> Display for TaskResult<T, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "Task '{name}'"),
+            None => write!(f, "Task {}", self.task_id),
+        }
+    }
+}
+
+/// Why a task did not succeed, see [`ExecutionResult::into_result`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskFailure<E> {
+    /// The task completed with an error
+    Error(E),
+
+    /// The task was skipped, see [`TaskOutcome::Skipped`]
+    Skipped,
+
+    /// The task was abandoned after exceeding its timeout
+    TimedOut,
+
+    /// The task panicked, with the panic message
+    Panicked(String),
+
+    /// The spawned task was aborted while running
+    Cancelled,
+
+    /// The task never started because the execution stopped early, see
+    /// [`ExecutionResult::unrun`]
+    NotRun,
+}
+
+/// Outcome of a single task within an execution step
+#[derive(Debug)]
+pub enum TaskOutcome<T, E> {
+    /// The task ran to completion, successfully or not
+    Completed(TaskResult<T, E>),
+
+    /// The task never ran because one of its dependencies failed or was skipped
+    Skipped(TaskId),
+
+    /// The task was abandoned after exceeding its timeout
+    TimedOut(TaskId),
+
+    /// The task panicked, with the panic message
+    Panicked(TaskId, String),
+
+    /// The spawned task was aborted while running
+    Cancelled(TaskId),
+}
+
+impl<T, E> TaskOutcome<T, E> {
+    pub fn task_id(&self) -> TaskId {
+        match self {
+            TaskOutcome::Completed(task_result) => task_result.task_id,
+            TaskOutcome::Skipped(task_id)
+            | TaskOutcome::TimedOut(task_id)
+            | TaskOutcome::Panicked(task_id, _)
+            | TaskOutcome::Cancelled(task_id) => *task_id,
+        }
+    }
+
+    /// Returns the task's result, or `None` if it was skipped
+    pub fn result(&self) -> Option<&Result<T, E>> {
+        self.as_completed().map(|task_result| &task_result.result)
+    }
+
+    pub fn as_completed(&self) -> Option<&TaskResult<T, E>> {
+        match self {
+            TaskOutcome::Completed(task_result) => Some(task_result),
+            _ => None,
+        }
+    }
+
+    pub fn into_completed(self) -> Option<TaskResult<T, E>> {
+        match self {
+            TaskOutcome::Completed(task_result) => Some(task_result),
+            _ => None,
+        }
+    }
+
+    pub fn is_skipped(&self) -> bool {
+        matches!(self, TaskOutcome::Skipped(_))
+    }
+
+    /// Transforms the result of a completed task, passing the other outcomes through
+    fn map_result<U, F>(self, f: impl FnOnce(Result<T, E>) -> Result<U, F>) -> TaskOutcome<U, F> {
+        match self {
+            TaskOutcome::Completed(task_result) => TaskOutcome::Completed(TaskResult {
+                task_id: task_result.task_id,
+                name: task_result.name,
+                group: task_result.group,
+                result: f(task_result.result),
+                duration: task_result.duration,
+                start_offset: task_result.start_offset,
+            }),
+            TaskOutcome::Skipped(task_id) => TaskOutcome::Skipped(task_id),
+            TaskOutcome::TimedOut(task_id) => TaskOutcome::TimedOut(task_id),
+            TaskOutcome::Panicked(task_id, message) => TaskOutcome::Panicked(task_id, message),
+            TaskOutcome::Cancelled(task_id) => TaskOutcome::Cancelled(task_id),
+        }
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, TaskOutcome::TimedOut(_))
+    }
+}
+
+impl<T: Display, E: Display> Display for TaskOutcome<T, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskOutcome::Completed(task_result) => match &task_result.result {
+                Ok(value) => write!(f, "{task_result} succeeded: {value}"),
+                Err(err) => write!(f, "{task_result} failed: {err}"),
+            },
+            TaskOutcome::Skipped(task_id) => write!(f, "Task {task_id} skipped"),
+            TaskOutcome::TimedOut(task_id) => write!(f, "Task {task_id} timed out"),
+            TaskOutcome::Panicked(task_id, message) => {
+                write!(f, "Task {task_id} panicked: {message}")
+            }
+            TaskOutcome::Cancelled(task_id) => write!(f, "Task {task_id} cancelled"),
+        }
+    }
+}
+
+/// Success, failure and skip counts of a step, timeouts and panics being failures
+fn step_counts<T, E>(step: &[TaskOutcome<T, E>]) -> (usize, usize, usize) {
+    step.iter().fold(
+        (0, 0, 0),
+        |(successful, failed, skipped), outcome| match outcome {
+            TaskOutcome::Completed(task_result) if task_result.result.is_ok() => {
+                (successful + 1, failed, skipped)
+            }
+            TaskOutcome::Skipped(_) => (successful, failed, skipped + 1),
+            _ => (successful, failed + 1, skipped),
+        },
+    )
 }
 
 /// Complete execution result with all task results organized by execution steps
 #[derive(Debug)]
 pub struct ExecutionResult<T, E> {
-    pub steps: Vec<Vec<TaskResult<T, E>>>,
+    /// One entry per step of the blueprint, at the same index, even if all of
+    /// its tasks were skipped. The outcomes of a step are sorted by task id.
+    pub steps: Vec<Vec<TaskOutcome<T, E>>>,
     pub total_tasks: usize,
     pub successful_tasks: usize,
     pub failed_tasks: usize,
+    pub skipped_tasks: usize,
+    /// Tasks of the blueprint that were never started, because the execution
+    /// stopped early, in blueprint order
+    pub unrun: Vec<TaskId>,
+    /// Timing of every step that ran, at the same index as [`steps`](Self::steps).
+    /// Empty with [`ExecutionMode::eager`](crate::ExecutionMode::eager), which doesn't
+    /// run the tasks step by step.
+    pub step_timings: Vec<StepTiming>,
+}
+
+/// An empty result, to be filled by [`TaskExecutor::execute_into`](crate::TaskExecutor::execute_into)
+impl<T, E> Default for ExecutionResult<T, E> {
+    fn default() -> Self {
+        Self {
+            steps: vec![],
+            total_tasks: 0,
+            successful_tasks: 0,
+            failed_tasks: 0,
+            skipped_tasks: 0,
+            unrun: vec![],
+            step_timings: vec![],
+        }
+    }
 }
 
 impl<T, E> ExecutionResult<T, E> {
-    /// Returns all successful task results
-    pub fn successful_results(&self) -> impl Iterator<Item = &TaskResult<T, E>> {
+    /// Number of tasks of the execution, whether they ran or not
+    pub fn len(&self) -> usize {
+        self.total_tasks
+    }
+
+    /// Whether the execution had no task at all
+    pub fn is_empty(&self) -> bool {
+        self.total_tasks == 0
+    }
+
+    /// Returns all completed task results
+    fn completed_results(&self) -> impl Iterator<Item = &TaskResult<T, E>> {
         self.steps
             .iter()
             .flat_map(|step| step.iter())
+            .filter_map(TaskOutcome::as_completed)
+    }
+
+    /// Iterates over the outcomes of each step along with the step's index, unlike the
+    /// flattening iterators such as [`successful_results`](Self::successful_results)
+    pub fn enumerate_steps(&self) -> impl Iterator<Item = (usize, &[TaskOutcome<T, E>])> {
+        self.steps.iter().map(Vec::as_slice).enumerate()
+    }
+
+    /// Returns all successful task results
+    pub fn successful_results(&self) -> impl Iterator<Item = &TaskResult<T, E>> {
+        self.completed_results()
             .filter(|result| result.result.is_ok())
     }
 
     /// Returns all failed task results
     pub fn failed_results(&self) -> impl Iterator<Item = &TaskResult<T, E>> {
+        self.completed_results()
+            .filter(|result| result.result.is_err())
+    }
+
+    /// Returns the index of the blueprint step the task belonged to
+    pub fn step_index(&self, task_id: &TaskId) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|step| step.iter().any(|outcome| outcome.task_id() == *task_id))
+    }
+
+    /// Returns the ids and values of the successful tasks
+    pub fn successes(&self) -> impl Iterator<Item = (TaskId, &T)> {
+        self.completed_results().filter_map(|task_result| {
+            task_result
+                .result
+                .as_ref()
+                .ok()
+                .map(|value| (task_result.task_id, value))
+        })
+    }
+
+    /// Returns the ids and errors of the tasks that completed with an error
+    pub fn failures(&self) -> impl Iterator<Item = (TaskId, &E)> {
+        self.completed_results().filter_map(|task_result| {
+            task_result
+                .result
+                .as_ref()
+                .err()
+                .map(|err| (task_result.task_id, err))
+        })
+    }
+
+    /// Returns the first task error in step order, if any task failed
+    pub fn first_error(&self) -> Option<(&TaskId, &E)> {
+        self.completed_results().find_map(|task_result| {
+            task_result
+                .result
+                .as_ref()
+                .err()
+                .map(|err| (&task_result.task_id, err))
+        })
+    }
+
+    /// Returns the completed task that ran the longest
+    pub fn slowest_task(&self) -> Option<&TaskResult<T, E>> {
+        self.completed_results()
+            .max_by_key(|task_result| task_result.duration)
+    }
+
+    /// Time from the start of the execution until the last completed task finished
+    pub fn total_wall_time(&self) -> Duration {
+        self.completed_results()
+            .map(|task_result| task_result.start_offset + task_result.duration)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Time between the end of step `index` and the start of the next one, spent
+    /// outside of any step, e.g. collecting results or running hooks
+    pub fn gap_between_steps(&self, index: usize) -> Option<Duration> {
+        let previous = self.step_timings.get(index)?;
+        let next = self.step_timings.get(index + 1)?;
+        Some(next.started.saturating_sub(previous.finished))
+    }
+
+    /// Numbers of (successful, failed) completed tasks of each group. Tasks
+    /// without a group are not counted.
+    pub fn by_group(&self) -> HashMap<String, (usize, usize)> {
+        let mut groups: HashMap<String, (usize, usize)> = HashMap::new();
+        for task_result in self.completed_results() {
+            let Some(group) = &task_result.group else {
+                continue;
+            };
+            let (successes, failures) = groups.entry(group.clone()).or_default();
+            if task_result.result.is_ok() {
+                *successes += 1;
+            } else {
+                *failures += 1;
+            }
+        }
+        groups
+    }
+
+    /// Sum of the durations of the completed tasks
+    pub fn total_compute_time(&self) -> Duration {
+        self.completed_results()
+            .map(|task_result| task_result.duration)
+            .sum()
+    }
+
+    /// How many tasks ran at the same time on average: the total compute time over
+    /// the [`total_wall_time`](Self::total_wall_time). Zero if nothing ran.
+    pub fn parallelism_efficiency(&self) -> f64 {
+        let wall_time = self.total_wall_time();
+        if wall_time.is_zero() {
+            return 0.0;
+        }
+        self.total_compute_time().as_secs_f64() / wall_time.as_secs_f64()
+    }
+
+    /// Whether every step of `blueprint` ran with all of its tasks, as opposed to an
+    /// execution that stopped early
+    pub fn completed_all_steps(&self, blueprint: &Blueprint) -> bool {
+        self.steps.len() == blueprint.step_count()
+            && self
+                .steps
+                .iter()
+                .zip(blueprint.iter_steps())
+                .all(|(outcomes, (_, task_ids))| outcomes.len() == task_ids.len())
+    }
+
+    /// Returns the ids of all skipped tasks
+    pub fn skipped_results(&self) -> impl Iterator<Item = TaskId> + '_ {
         self.steps
             .iter()
             .flat_map(|step| step.iter())
-            .filter(|result| result.result.is_err())
+            .filter(|outcome| outcome.is_skipped())
+            .map(TaskOutcome::task_id)
+    }
+
+    /// Consumes the result, returning the values of all successful tasks in step order
+    pub fn into_successful_values(self) -> Vec<T> {
+        self.steps
+            .into_iter()
+            .flatten()
+            .filter_map(|outcome| match outcome {
+                TaskOutcome::Completed(task_result) => task_result.result.ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Transforms the value of every successful task, keeping the step structure,
+    /// the timings and the counts
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> ExecutionResult<U, E> {
+        self.map_results(|result| result.map(&mut f))
+    }
+
+    /// Transforms the error of every failed task, keeping the step structure,
+    /// the timings and the counts
+    pub fn map_err<F>(self, mut f: impl FnMut(E) -> F) -> ExecutionResult<T, F> {
+        self.map_results(|result| result.map_err(&mut f))
+    }
+
+    fn map_results<U, F>(
+        self,
+        mut f: impl FnMut(Result<T, E>) -> Result<U, F>,
+    ) -> ExecutionResult<U, F> {
+        ExecutionResult {
+            steps: self
+                .steps
+                .into_iter()
+                .map(|step| {
+                    step.into_iter()
+                        .map(|outcome| outcome.map_result(&mut f))
+                        .collect()
+                })
+                .collect(),
+            total_tasks: self.total_tasks,
+            successful_tasks: self.successful_tasks,
+            failed_tasks: self.failed_tasks,
+            skipped_tasks: self.skipped_tasks,
+            unrun: self.unrun,
+            step_timings: self.step_timings,
+        }
+    }
+
+    /// All successful values with their task ids when every task succeeded, otherwise
+    /// every task that didn't, with the reason. Tasks skipped by their own
+    /// [condition](crate::Task::when) count as not succeeded too.
+    #[allow(clippy::type_complexity)]
+    pub fn into_result(self) -> Result<Vec<(TaskId, T)>, Vec<(TaskId, TaskFailure<E>)>> {
+        let mut successes = Vec::new();
+        let mut failures = Vec::new();
+        for outcome in self.steps.into_iter().flatten() {
+            let (task_id, failure) = match outcome {
+                TaskOutcome::Completed(task_result) => match task_result.result {
+                    Ok(value) => {
+                        successes.push((task_result.task_id, value));
+                        continue;
+                    }
+                    Err(error) => (task_result.task_id, TaskFailure::Error(error)),
+                },
+                TaskOutcome::Skipped(task_id) => (task_id, TaskFailure::Skipped),
+                TaskOutcome::TimedOut(task_id) => (task_id, TaskFailure::TimedOut),
+                TaskOutcome::Panicked(task_id, message) => {
+                    (task_id, TaskFailure::Panicked(message))
+                }
+                TaskOutcome::Cancelled(task_id) => (task_id, TaskFailure::Cancelled),
+            };
+            failures.push((task_id, failure));
+        }
+        failures.extend(
+            self.unrun
+                .into_iter()
+                .map(|task_id| (task_id, TaskFailure::NotRun)),
+        );
+
+        if failures.is_empty() {
+            Ok(successes)
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Multi-line summary of the task counts of the whole run and of each step
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        self.write_summary(&mut summary, |_, _| Ok(()))
+            .expect("Writing to a String cannot fail");
+        summary
+    }
+
+    /// Writes the summary, calling `write_step` after the counts of each step
+    fn write_summary<W: Write>(
+        &self,
+        out: &mut W,
+        mut write_step: impl FnMut(&mut W, &[TaskOutcome<T, E>]) -> std::fmt::Result,
+    ) -> std::fmt::Result {
+        write!(
+            out,
+            "{} tasks in {} steps: {} succeeded, {} failed, {} skipped",
+            self.total_tasks,
+            self.steps.len(),
+            self.successful_tasks,
+            self.failed_tasks,
+            self.skipped_tasks
+        )?;
+        for (index, step) in self.steps.iter().enumerate() {
+            let (successful, failed, skipped) = step_counts(step);
+            write!(
+                out,
+                "\n  Step {}: {successful} succeeded, {failed} failed, {skipped} skipped",
+                index + 1
+            )?;
+            write_step(out, step)?;
+        }
+        Ok(())
     }
 
     /// Returns true if all tasks completed successfully
     pub fn all_successful(&self) -> bool {
-        self.failed_tasks == 0
+        self.failed_tasks == 0 && self.skipped_tasks == 0
+    }
+}
+
+/// Completed results out of the flattened steps of `I`
+type CompletedResults<I, O, R> = FilterMap<Flatten<I>, fn(O) -> Option<R>>;
+
+/// Iterates over the results of the completed tasks, step after step
+impl<T, E> IntoIterator for ExecutionResult<T, E> {
+    type Item = TaskResult<T, E>;
+    type IntoIter = CompletedResults<
+        std::vec::IntoIter<Vec<TaskOutcome<T, E>>>,
+        TaskOutcome<T, E>,
+        TaskResult<T, E>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.steps
+            .into_iter()
+            .flatten()
+            .filter_map(TaskOutcome::into_completed)
+    }
+}
+
+/// Iterates over the results of the completed tasks, step after step
+impl<'a, T, E> IntoIterator for &'a ExecutionResult<T, E> {
+    type Item = &'a TaskResult<T, E>;
+    type IntoIter = CompletedResults<
+        std::slice::Iter<'a, Vec<TaskOutcome<T, E>>>,
+        &'a TaskOutcome<T, E>,
+        &'a TaskResult<T, E>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.steps
+            .iter()
+            .flatten()
+            .filter_map(TaskOutcome::as_completed)
+    }
+}
+
+/// Summary of the run followed by the outcome of every task, grouped by step
+impl<T: Display, E: Display> Display for ExecutionResult<T, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write_summary(f, |f, step| {
+            step.iter()
+                .try_for_each(|outcome| write!(f, "\n    {outcome}"))
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, E: serde::Serialize> serde::Serialize for TaskResult<T, E> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("TaskResult", 6)?;
+        state.serialize_field("task_id", &self.task_id)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("group", &self.group)?;
+        state.serialize_field("result", &self.result)?;
+        state.serialize_field("duration", &self.duration)?;
+        state.serialize_field("start_offset", &self.start_offset)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for StepTiming {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("StepTiming", 2)?;
+        state.serialize_field("started", &self.started)?;
+        state.serialize_field("finished", &self.finished)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, E: serde::Serialize> serde::Serialize for TaskOutcome<T, E> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TaskOutcome::Completed(task_result) => {
+                serializer.serialize_newtype_variant("TaskOutcome", 0, "Completed", task_result)
+            }
+            TaskOutcome::Skipped(task_id) => {
+                serializer.serialize_newtype_variant("TaskOutcome", 1, "Skipped", task_id)
+            }
+            TaskOutcome::TimedOut(task_id) => {
+                serializer.serialize_newtype_variant("TaskOutcome", 2, "TimedOut", task_id)
+            }
+            TaskOutcome::Panicked(task_id, message) => serializer.serialize_newtype_variant(
+                "TaskOutcome",
+                3,
+                "Panicked",
+                &(task_id, message),
+            ),
+            TaskOutcome::Cancelled(task_id) => {
+                serializer.serialize_newtype_variant("TaskOutcome", 4, "Cancelled", task_id)
+            }
+        }
+    }
+}
+
+/// The steps are serialized as `{ "step": index, "tasks": [...] }` objects,
+/// so consumers don't have to rely on the position of each step
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, E: serde::Serialize> serde::Serialize for ExecutionResult<T, E> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeStruct};
+
+        struct Step<'r, T, E>(usize, &'r [TaskOutcome<T, E>]);
+
+        impl<T: serde::Serialize, E: serde::Serialize> serde::Serialize for Step<'_, T, E> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut state = serializer.serialize_map(Some(2))?;
+                state.serialize_entry("step", &self.0)?;
+                state.serialize_entry("tasks", self.1)?;
+                state.end()
+            }
+        }
+
+        let steps: Vec<_> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| Step(index, step))
+            .collect();
+
+        let mut state = serializer.serialize_struct("ExecutionResult", 7)?;
+        state.serialize_field("total_tasks", &self.total_tasks)?;
+        state.serialize_field("successful_tasks", &self.successful_tasks)?;
+        state.serialize_field("failed_tasks", &self.failed_tasks)?;
+        state.serialize_field("skipped_tasks", &self.skipped_tasks)?;
+        state.serialize_field("unrun", &self.unrun)?;
+        state.serialize_field("steps", &steps)?;
+        state.serialize_field("step_timings", &self.step_timings)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn completed(name: &str, result: Result<i32, String>) -> TaskOutcome<i32, String> {
+        TaskOutcome::Completed(TaskResult {
+            task_id: TaskId::generate(),
+            name: Some(name.to_string()),
+            group: None,
+            result,
+            duration: Duration::ZERO,
+            start_offset: Duration::ZERO,
+        })
+    }
+
+    fn execution_result() -> ExecutionResult<i32, String> {
+        ExecutionResult {
+            steps: vec![
+                vec![
+                    completed("a", Ok(1)),
+                    completed("b", Err("boom".to_string())),
+                ],
+                vec![TaskOutcome::Skipped(TaskId::generate())],
+            ],
+            total_tasks: 3,
+            successful_tasks: 1,
+            failed_tasks: 1,
+            skipped_tasks: 1,
+            unrun: vec![],
+            step_timings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_successes_and_failures() {
+        let result = execution_result();
+        let first_id = result.steps[0][0].task_id();
+        let second_id = result.steps[0][1].task_id();
+
+        assert_eq!(result.successes().collect::<Vec<_>>(), [(first_id, &1)]);
+        assert_eq!(
+            result.failures().collect::<Vec<_>>(),
+            [(second_id, &"boom".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_into_result() {
+        let result = execution_result();
+        let failed_id = result.steps[0][1].task_id();
+        let skipped_id = result.steps[1][0].task_id();
+        assert_eq!(
+            result.into_result(),
+            Err(vec![
+                (failed_id, TaskFailure::Error("boom".to_string())),
+                (skipped_id, TaskFailure::Skipped)
+            ])
+        );
+
+        let mut result = execution_result();
+        result.steps[0].pop();
+        result.steps.pop();
+        let first_id = result.steps[0][0].task_id();
+        assert_eq!(result.into_result(), Ok(vec![(first_id, 1)]));
+    }
+
+    #[test]
+    fn test_into_result_without_failed_task() {
+        let (panicked_id, timed_out_id, unrun_id) =
+            (TaskId::generate(), TaskId::generate(), TaskId::generate());
+        let result = ExecutionResult::<i32, String> {
+            steps: vec![vec![
+                completed("a", Ok(1)),
+                TaskOutcome::Panicked(panicked_id, "boom".to_string()),
+                TaskOutcome::TimedOut(timed_out_id),
+            ]],
+            unrun: vec![unrun_id],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            result.into_result(),
+            Err(vec![
+                (panicked_id, TaskFailure::Panicked("boom".to_string())),
+                (timed_out_id, TaskFailure::TimedOut),
+                (unrun_id, TaskFailure::NotRun)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_enumerate_steps() {
+        let result = execution_result();
+        let steps: Vec<_> = result
+            .enumerate_steps()
+            .map(|(index, outcomes)| (index, outcomes.len()))
+            .collect();
+
+        assert_eq!(steps, [(0, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn test_map() {
+        let result = execution_result().map(|value| value.to_string());
+
+        assert_eq!(result.total_tasks, 3);
+        assert_eq!(result.successful_tasks, 1);
+        assert_eq!(result.failed_tasks, 1);
+        assert_eq!(result.skipped_tasks, 1);
+        assert_eq!(result.steps[0][0].result(), Some(&Ok("1".to_string())));
+        assert_eq!(result.steps[0][1].result(), Some(&Err("boom".to_string())));
+        assert!(result.steps[1][0].is_skipped());
+
+        let result = result.map_err(|err| err.len());
+        assert_eq!(result.steps[0][1].result(), Some(&Err(4)));
+        assert_eq!(result.steps[0][0].result(), Some(&Ok("1".to_string())));
+    }
+
+    #[test]
+    fn test_timing_aggregates() {
+        let timed = |start_offset: u64, duration: u64| {
+            TaskOutcome::<i32, String>::Completed(TaskResult {
+                task_id: TaskId::generate(),
+                name: None,
+                group: None,
+                result: Ok(0),
+                duration: Duration::from_millis(duration),
+                start_offset: Duration::from_millis(start_offset),
+            })
+        };
+        let mut result = ExecutionResult::default();
+        assert_eq!(result.parallelism_efficiency(), 0.0);
+
+        result.steps = vec![vec![timed(0, 100), timed(0, 100)], vec![timed(100, 50)]];
+        assert_eq!(result.total_compute_time(), Duration::from_millis(250));
+        assert_eq!(result.total_wall_time(), Duration::from_millis(150));
+        let efficiency = result.parallelism_efficiency();
+        assert!((1.66..1.67).contains(&efficiency), "{efficiency}");
+    }
+
+    #[test]
+    fn test_len_and_iteration() {
+        let empty = ExecutionResult::<i32, String>::default();
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.into_iter().count(), 0);
+
+        let result = execution_result();
+        assert!(!result.is_empty());
+        assert_eq!(result.len(), 3);
+        // The skipped task has no result
+        assert_eq!((&result).into_iter().count(), 2);
+        let names: Vec<_> = result
+            .into_iter()
+            .map(|task_result| task_result.name.unwrap())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            execution_result().summary(),
+            "3 tasks in 2 steps: 1 succeeded, 1 failed, 1 skipped\n  \
+             Step 1: 1 succeeded, 1 failed, 0 skipped\n  \
+             Step 2: 0 succeeded, 0 failed, 1 skipped"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let display = execution_result().to_string();
+        let lines: Vec<_> = display.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[2], "    Task 'a' succeeded: 1");
+        assert_eq!(lines[3], "    Task 'b' failed: boom");
+        assert!(lines[5].ends_with(" skipped"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let mut result = execution_result();
+        result.step_timings.push(StepTiming {
+            started: Duration::from_millis(2),
+            finished: Duration::from_millis(5),
+        });
+        let first_id = result.steps[0][0].task_id();
+
+        let json = serde_json::to_value(&result).unwrap();
+
+        assert_eq!(json["total_tasks"], 3);
+        let first_step = &json["steps"][0];
+        assert_eq!(first_step["step"], 0);
+        let completed = &first_step["tasks"][0]["Completed"];
+        assert_eq!(completed["task_id"], first_id.to_string());
+        assert_eq!(completed["result"]["Ok"], 1);
+        assert_eq!(first_step["tasks"][1]["Completed"]["result"]["Err"], "boom");
+        assert_eq!(json["steps"][1]["step"], 1);
+        assert!(json["steps"][1]["tasks"][0]["Skipped"].is_string());
+        assert_eq!(completed["start_offset"]["secs"], 0);
+        assert_eq!(json["step_timings"][0]["started"]["nanos"], 2_000_000);
+        assert_eq!(json["step_timings"][0]["finished"]["nanos"], 5_000_000);
     }
 }
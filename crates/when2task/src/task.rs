@@ -1,16 +1,98 @@
-use crate::{Dependency, TaskId};
+use crate::{BlueprintError, BuildError, Dependency, TaskId};
 use derive_getters::Getters;
+use futures::channel::oneshot;
+use futures::{FutureExt, TryFutureExt};
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub type UnitTask<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
 
+/// Reports how far a task created with [`Task::from_fn_with_progress`] got, from `0.0`
+/// to `1.0`, to the [`on_progress`](crate::TaskExecutor::on_progress) hook. Reports are
+/// dropped when the executor has no such hook.
+#[derive(Clone, Default)]
+pub struct ProgressSender {
+    report: Option<Arc<dyn Fn(f32) + Send + Sync>>,
+}
+
+impl ProgressSender {
+    pub(crate) fn new(report: impl Fn(f32) + Send + Sync + 'static) -> Self {
+        Self {
+            report: Some(Arc::new(report)),
+        }
+    }
+
+    pub fn send(&self, progress: f32) {
+        if let Some(report) = &self.report {
+            report(progress);
+        }
+    }
+}
+
+/// Output of tasks with different output types, see [`Task::into_any`]
+pub type AnyOutput = Box<dyn Any + Send>;
+
+/// Like [`UnitTask`], without the `Send` bound
+pub type LocalUnitTask<T, E> = Pin<Box<dyn Future<Output = Result<T, E>>>>;
+
+/// Looks up the output of an already completed task
+pub(crate) type OutputLookup<'r, T> = &'r dyn Fn(&TaskId) -> Option<&'r T>;
+
+/// Unit of synchronous work handed to a thread pool
+pub(crate) type BlockingJob<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Runs a blocking job, either in place or on a thread pool
+pub(crate) type BlockingRunner<'a> = Arc<dyn Fn(BlockingJob<'a>) + Send + Sync + 'a>;
+
+type InputTask<'a, T, E> = Box<dyn FnOnce(OutputLookup<'_, T>) -> UnitTask<'a, T, E> + Send + 'a>;
+
+type BlockingTask<'a, T, E> = Box<dyn FnOnce(BlockingRunner<'a>) -> UnitTask<'a, T, E> + Send + 'a>;
+
+type FactoryTask<'a, T, E> = Box<dyn Fn(ProgressSender) -> UnitTask<'a, T, E> + Send + Sync + 'a>;
+
+type Condition<'a> = Arc<dyn Fn() -> bool + Send + Sync + 'a>;
+
+pub(crate) enum TaskBody<'a, T, E> {
+    /// The future is fully self-contained
+    Future(UnitTask<'a, T, E>),
+    /// The future is created once the outputs of the dependencies are available
+    WithInputs(InputTask<'a, T, E>),
+    /// Synchronous work, the future resolves once the work ran on the runner
+    Blocking(BlockingTask<'a, T, E>),
+    /// A fresh future is created for every execution
+    Factory(FactoryTask<'a, T, E>),
+}
+
 #[derive(Getters)]
 pub struct Task<'a, T, E> {
     id: TaskId,
     #[getter(skip)]
-    task: UnitTask<'a, T, E>,
+    task: TaskBody<'a, T, E>,
+    /// Dependencies of the task, structure included; use [`Dependency::iter`] for the
+    /// flattened ids
     dependencies: Dependency,
+    /// Human-readable label, purely informational
+    name: Option<String>,
+    /// Logical group the task belongs to, purely informational
+    group: Option<String>,
+    /// Arbitrary attributes, purely informational
+    metadata: HashMap<String, String>,
+    /// Tasks with a higher priority are started first within a step
+    priority: i32,
+    /// Maximum time the task may run before it is abandoned
+    timeout: Option<Duration>,
+    /// Whether the task runs alone in its own step
+    exclusive: bool,
+    /// Expected running time, weighing the critical path of the blueprint
+    cost: Option<Duration>,
+    #[getter(skip)]
+    condition: Option<Condition<'a>>,
 }
 
 impl<'a, T, E> Task<'a, T, E> {
@@ -18,20 +100,832 @@ impl<'a, T, E> Task<'a, T, E> {
         task: F,
         dependencies: impl Into<Dependency>,
     ) -> Self {
-        let id = TaskId::generate();
+        Self::from_body(TaskBody::Future(Box::pin(task)), dependencies.into())
+    }
+
+    /// Like [`Task::new`], for a future that is already boxed, which is stored as is
+    /// rather than boxed a second time
+    pub fn from_boxed(future: UnitTask<'a, T, E>, dependencies: impl Into<Dependency>) -> Self {
+        Self::from_body(TaskBody::Future(future), dependencies.into())
+    }
+
+    /// Returns a builder to configure every attribute of a task at once
+    pub fn builder() -> TaskBuilder<'a, T, E> {
+        TaskBuilder::default()
+    }
+
+    fn from_body(task: TaskBody<'a, T, E>, dependencies: Dependency) -> Self {
+        Self {
+            id: TaskId::generate(),
+            task,
+            dependencies,
+            name: None,
+            group: None,
+            metadata: HashMap::new(),
+            priority: 0,
+            timeout: None,
+            exclusive: false,
+            cost: None,
+            condition: None,
+        }
+    }
+
+    /// Convenience method to create a task with no dependencies
+    pub fn new_independent<F: Future<Output = Result<T, E>> + Send + 'a>(task: F) -> Self {
+        Self::new(task, [])
+    }
+
+    /// Creates a task that receives the outputs of its dependencies.
+    ///
+    /// The closure is called by the executor right before the task is scheduled,
+    /// with a map of every dependency that completed successfully to its output.
+    /// Outputs are cloned out of the execution results, hence the `T: Clone` bound;
+    /// wrap expensive outputs in an `Arc` to keep the clone cheap.
+    pub fn with_inputs<F, Fut>(task: F, dependencies: impl Into<Dependency>) -> Self
+    where
+        T: Clone,
+        F: FnOnce(HashMap<TaskId, T>) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        let dependencies = dependencies.into();
+        let dependency_ids: Vec<TaskId> = dependencies.iter().collect();
+
+        let body = TaskBody::WithInputs(Box::new(move |lookup: OutputLookup<'_, T>| {
+            let inputs = dependency_ids
+                .iter()
+                .filter_map(|id| lookup(id).map(|output| (*id, output.clone())))
+                .collect();
+            Box::pin(task(inputs)) as UnitTask<'a, T, E>
+        }));
+        Self::from_body(body, dependencies)
+    }
+
+    /// Replaces the generated id with a chosen one, typically from
+    /// [`TaskId::from_name`] or [`TaskId::from_u128`], so dependents can refer to
+    /// the task before it exists. The caller is responsible for keeping ids
+    /// unique: the executor rejects a second task with the same id.
+    pub fn with_id(mut self, id: TaskId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Attaches a human-readable name to the task. The name does not affect
+    /// scheduling or the task's identity.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Puts the task in a named group, e.g. a stage of the pipeline, to get
+    /// per-group counts with [`ExecutionResult::by_group`](crate::ExecutionResult::by_group).
+    /// Like the name, the group does not affect scheduling.
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    /// Attaches a metadata attribute to the task. Like the name, metadata
+    /// does not affect scheduling.
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.insert_metadata(key, value);
+        self
+    }
+
+    /// Sets the priority of the task, `0` by default. Priority only decides the
+    /// order in which tasks of a step are admitted under a concurrency cap
+    /// ([`ExecutionMode::bounded`](crate::ExecutionMode::bounded)); without one
+    /// every task of a step starts at once and priority has no effect.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Limits how long the task may run. A task exceeding its timeout is abandoned
+    /// and recorded as [`TaskOutcome::TimedOut`](crate::TaskOutcome::TimedOut). The time
+    /// counts from when the executor starts the task, not from when its step starts.
+    /// Timeouts rely on the tokio timer, so the runtime must have time enabled.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Makes the task run alone, e.g. when it touches a global resource. The blueprint
+    /// moves an exclusive task out of the step it would share with other tasks into a
+    /// singleton step of its own, placed right after the rest of that step.
+    /// [`ExecutionMode::eager`](crate::ExecutionMode::eager) ignores steps, and with them
+    /// this flag.
+    pub fn with_exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Sets how long the task is expected to run, so that
+    /// [`Blueprint::critical_path`](crate::Blueprint::critical_path) weighs
+    /// dependency chains by their expected time. Like the name, the cost does not
+    /// affect scheduling.
+    pub fn with_cost(mut self, cost: Duration) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Only runs the task if `predicate` returns `true`, e.g. to honour a feature flag.
+    /// The executor evaluates it right before starting the task. When it returns `false`
+    /// the task is recorded as [`TaskOutcome::Skipped`](crate::TaskOutcome::Skipped), but
+    /// unlike a task skipped after a failure, its dependents still run, as if it had
+    /// succeeded. Dependents created with [`Task::with_inputs`] don't receive an output for it.
+    pub fn when(mut self, predicate: impl Fn() -> bool + Send + Sync + 'a) -> Self {
+        self.condition = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Resolves the [`Dependency::Group`] dependencies of the task, failing on the
+    /// first group without tasks
+    pub(crate) fn resolve_groups(
+        &mut self,
+        groups: &HashMap<String, Vec<TaskId>>,
+    ) -> Result<(), BlueprintError> {
+        match self.dependencies.resolve_groups(groups).into_iter().next() {
+            Some(group) => Err(BlueprintError::UnknownGroup(self.id, group)),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops depending on `task_id`, e.g. because it was removed from the executor
+    pub(crate) fn remove_dependency(&mut self, task_id: &TaskId) {
+        self.dependencies.remove_task(task_id);
+    }
+
+    /// Whether the condition set with [`Task::when`], if any, allows the task to run
+    pub(crate) fn should_run(&self) -> bool {
+        self.condition.as_ref().is_none_or(|predicate| predicate())
+    }
+
+    /// Sets a metadata attribute, returning the previous value for the key
+    pub fn insert_metadata(&mut self, key: &str, value: &str) -> Option<String> {
+        self.metadata.insert(key.to_string(), value.to_string())
+    }
+
+    /// Creates a task from a blocking closure, for CPU-bound or otherwise synchronous work.
+    ///
+    /// With [`ExecutionMode::blocking`](crate::ExecutionMode::blocking) the closure runs on
+    /// tokio's blocking thread pool and with [`ExecutionMode::parallel`](crate::ExecutionMode::parallel)
+    /// on the rayon thread pool. In every other mode it runs in place on the thread driving
+    /// the execution, blocking it until the closure returns. A closure running in place can't
+    /// be interrupted: when it overruns its [timeout](Task::with_timeout), the task is recorded
+    /// as timed out once it returns. A panic in the closure is recorded like a panic in a future.
+    pub fn from_blocking<F>(task: F, dependencies: impl Into<Dependency>) -> Self
+    where
+        T: Send + 'a,
+        E: Send + 'a,
+        F: FnOnce() -> Result<T, E> + Send + 'a,
+    {
+        let body = TaskBody::Blocking(Box::new(move |run: BlockingRunner<'a>| {
+            // The closure is only handed to the runner once the executor polls the task,
+            // after admitting it and starting its timeout
+            Box::pin(async move {
+                let (sender, receiver) = oneshot::channel();
+                run(Box::new(move || {
+                    let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(task)));
+                }));
+                match receiver.await {
+                    Ok(Ok(result)) => result,
+                    // Surface the panic where the executor expects it, in the future
+                    Ok(Err(payload)) => panic::resume_unwind(payload),
+                    Err(_) => panic!("Blocking task was dropped before it completed"),
+                }
+            }) as UnitTask<'a, T, E>
+        }));
+        Self::from_body(body, dependencies.into())
+    }
+
+    /// Creates a task from a closure producing its future. Unlike other tasks, it
+    /// can be executed repeatedly with [`TaskExecutor::execute_ref`](crate::TaskExecutor::execute_ref),
+    /// each run calling `factory` for a new future.
+    pub fn from_fn<F, Fut>(factory: F, dependencies: impl Into<Dependency>) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        Self::from_fn_with_progress(move |_| factory(), dependencies)
+    }
+
+    /// Like [`Task::from_fn`], with `factory` receiving a [`ProgressSender`] to report
+    /// how far the task got. Tasks created in any other way don't report progress.
+    pub fn from_fn_with_progress<F, Fut>(factory: F, dependencies: impl Into<Dependency>) -> Self
+    where
+        F: Fn(ProgressSender) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        let body = TaskBody::Factory(Box::new(move |progress| {
+            Box::pin(factory(progress)) as UnitTask<'a, T, E>
+        }));
+        Self::from_body(body, dependencies.into())
+    }
+
+    /// Whether the task can be executed more than once, see [`Task::from_fn`]
+    pub fn is_repeatable(&self) -> bool {
+        matches!(self.task, TaskBody::Factory(_))
+    }
+
+    /// Creates a single-use copy of a task built with [`Task::from_fn`], with the
+    /// same id and attributes and a fresh future reporting to `progress`
+    pub(crate) fn instantiate(&self, progress: ProgressSender) -> Option<Self> {
+        let TaskBody::Factory(factory) = &self.task else {
+            return None;
+        };
+        Some(Self {
+            id: self.id,
+            task: TaskBody::Future(factory(progress)),
+            dependencies: self.dependencies.clone(),
+            name: self.name.clone(),
+            group: self.group.clone(),
+            metadata: self.metadata.clone(),
+            priority: self.priority,
+            timeout: self.timeout,
+            exclusive: self.exclusive,
+            cost: self.cost,
+            condition: self.condition.clone(),
+        })
+    }
+
+    /// Transforms the output of the task on success, keeping its id, dependencies
+    /// and other attributes. A task created with [`Task::from_fn`] is no longer
+    /// repeatable once mapped, nor does it report progress.
+    ///
+    /// The inputs of a task created with [`Task::with_inputs`] are outputs of the same
+    /// type as its own, so once mapped it receives no inputs at all. Use
+    /// [`Task::try_map`] to reject such tasks instead.
+    pub fn map<U, F>(self, f: F) -> Task<'a, U, E>
+    where
+        T: 'a,
+        E: 'a,
+        F: FnOnce(T) -> U + Send + 'a,
+    {
+        let Ok(task) = self.map_body(|body| {
+            Ok::<_, Infallible>(match body {
+                TaskBody::Future(task) => {
+                    TaskBody::Future(Box::pin(task.map(|result| result.map(f))))
+                }
+                TaskBody::WithInputs(task) => {
+                    TaskBody::WithInputs(Box::new(move |_: OutputLookup<'_, U>| {
+                        Box::pin(task(&|_| None).map(|result| result.map(f)))
+                    }))
+                }
+                TaskBody::Blocking(task) => TaskBody::Blocking(Box::new(move |run| {
+                    Box::pin(task(run).map(|result| result.map(f)))
+                })),
+                TaskBody::Factory(factory) => TaskBody::Future(Box::pin(
+                    factory(Default::default()).map(|result| result.map(f)),
+                )),
+            })
+        });
+        task
+    }
+
+    /// Like [`Task::map`], but fails with [`BuildError::OutputTypeFixed`] if the task
+    /// was created with [`Task::with_inputs`] rather than dropping its inputs
+    pub fn try_map<U, F>(self, f: F) -> Result<Task<'a, U, E>, BuildError>
+    where
+        T: 'a,
+        E: 'a,
+        F: FnOnce(T) -> U + Send + 'a,
+    {
+        self.ensure_no_inputs()?;
+        Ok(self.map(f))
+    }
+
+    /// Boxes the output of the task, so that tasks with different output types can
+    /// share an executor. Outputs are recovered with
+    /// [`TaskResult::downcast_ref`](crate::TaskResult::downcast_ref).
+    ///
+    /// A task created with [`Task::with_inputs`] receives no inputs once boxed, like
+    /// with [`Task::map`].
+    pub fn into_any(self) -> Task<'a, AnyOutput, E>
+    where
+        T: Send + 'static,
+        E: 'a,
+    {
+        self.map(|value| Box::new(value) as AnyOutput)
+    }
+
+    /// Feeds the output of the task into `f` on success and runs the future it
+    /// returns as part of the same task, keeping its id, dependencies and other
+    /// attributes. An error of either stage ends the task.
+    ///
+    /// A task created with [`Task::with_inputs`] receives no inputs once chained, like
+    /// with [`Task::map`]. Use [`Task::try_then`] to reject such tasks instead.
+    pub fn then<U, Fut, F>(self, f: F) -> Task<'a, U, E>
+    where
+        T: 'a,
+        E: 'a,
+        F: FnOnce(T) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<U, E>> + Send + 'a,
+    {
+        let Ok(task) = self.map_body(|body| {
+            Ok::<_, Infallible>(match body {
+                TaskBody::Future(task) => TaskBody::Future(Box::pin(task.and_then(f))),
+                TaskBody::WithInputs(task) => {
+                    TaskBody::WithInputs(Box::new(move |_: OutputLookup<'_, U>| {
+                        Box::pin(task(&|_| None).and_then(f))
+                    }))
+                }
+                TaskBody::Blocking(task) => {
+                    TaskBody::Blocking(Box::new(move |run| Box::pin(task(run).and_then(f))))
+                }
+                TaskBody::Factory(factory) => {
+                    TaskBody::Future(Box::pin(factory(Default::default()).and_then(f)))
+                }
+            })
+        });
+        task
+    }
+
+    /// Like [`Task::then`], but fails with [`BuildError::OutputTypeFixed`] if the task
+    /// was created with [`Task::with_inputs`] rather than dropping its inputs
+    pub fn try_then<U, Fut, F>(self, f: F) -> Result<Task<'a, U, E>, BuildError>
+    where
+        T: 'a,
+        E: 'a,
+        F: FnOnce(T) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<U, E>> + Send + 'a,
+    {
+        self.ensure_no_inputs()?;
+        Ok(self.then(f))
+    }
+
+    /// Fails for a task created with [`Task::with_inputs`], whose output type can't change
+    fn ensure_no_inputs(&self) -> Result<(), BuildError> {
+        match self.task {
+            TaskBody::WithInputs(_) => Err(BuildError::OutputTypeFixed(self.id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Transforms the error of the task on failure, keeping its id, dependencies
+    /// and other attributes. A task created with [`Task::from_fn`] is no longer
+    /// repeatable once mapped, nor does it report progress.
+    pub fn map_err<F, G>(self, f: G) -> Task<'a, T, F>
+    where
+        T: 'a,
+        E: 'a,
+        G: FnOnce(E) -> F + Send + 'a,
+    {
+        let Ok(task) = self.map_body(|body| {
+            Ok::<_, Infallible>(match body {
+                TaskBody::Future(task) => {
+                    TaskBody::Future(Box::pin(task.map(|result| result.map_err(f))))
+                }
+                TaskBody::WithInputs(task) => TaskBody::WithInputs(Box::new(move |lookup| {
+                    Box::pin(task(lookup).map(|result| result.map_err(f)))
+                })),
+                TaskBody::Blocking(task) => TaskBody::Blocking(Box::new(move |run| {
+                    Box::pin(task(run).map(|result| result.map_err(f)))
+                })),
+                TaskBody::Factory(factory) => TaskBody::Future(Box::pin(
+                    factory(Default::default()).map(|result| result.map_err(f)),
+                )),
+            })
+        });
+        task
+    }
+
+    /// Rebuilds the task around a transformed body, unless the transformation fails
+    fn map_body<U, F, X>(
+        self,
+        f: impl FnOnce(TaskBody<'a, T, E>) -> Result<TaskBody<'a, U, F>, X>,
+    ) -> Result<Task<'a, U, F>, X> {
+        Ok(Task {
+            id: self.id,
+            task: f(self.task)?,
+            dependencies: self.dependencies,
+            name: self.name,
+            group: self.group,
+            metadata: self.metadata,
+            priority: self.priority,
+            timeout: self.timeout,
+            exclusive: self.exclusive,
+            cost: self.cost,
+            condition: self.condition,
+        })
+    }
+
+    pub(crate) fn into_task(
+        self,
+        lookup: OutputLookup<'_, T>,
+        run_blocking: BlockingRunner<'a>,
+        progress: ProgressSender,
+    ) -> UnitTask<'a, T, E> {
+        match self.task {
+            TaskBody::Future(task) => task,
+            TaskBody::WithInputs(task) => task(lookup),
+            TaskBody::Blocking(task) => task(run_blocking),
+            TaskBody::Factory(factory) => factory(progress),
+        }
+    }
+}
+
+impl<'a, E> Task<'a, (), E> {
+    /// Creates a task doing nothing, completing as soon as its dependencies did.
+    /// It serves as a synchronization point: depending on the barrier is depending
+    /// on everything the barrier depends on.
+    pub fn barrier(dependencies: impl Into<Dependency>) -> Self {
+        Self::new(async { Ok(()) }, dependencies)
+    }
+}
+
+/// A task whose future is not `Send`, executed by a [`LocalTaskExecutor`](crate::LocalTaskExecutor)
+#[derive(Getters)]
+pub struct LocalTask<T, E> {
+    id: TaskId,
+    #[getter(skip)]
+    task: LocalUnitTask<T, E>,
+    dependencies: Dependency,
+    name: Option<String>,
+}
 
+impl<T, E> LocalTask<T, E> {
+    pub fn new<F>(task: F, dependencies: impl Into<Dependency>) -> Self
+    where
+        F: Future<Output = Result<T, E>> + 'static,
+    {
         Self {
-            id,
+            id: TaskId::generate(),
             task: Box::pin(task),
             dependencies: dependencies.into(),
+            name: None,
         }
     }
 
     /// Convenience method to create a task with no dependencies
-    pub fn new_independent<F: Future<Output = Result<T, E>> + Send + 'a>(task: F) -> Self {
+    pub fn new_independent<F: Future<Output = Result<T, E>> + 'static>(task: F) -> Self {
         Self::new(task, [])
     }
-    pub(crate) fn into_task(self) -> UnitTask<'a, T, E> {
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn into_task(self) -> LocalUnitTask<T, E> {
         self.task
     }
 }
+
+/// Builder for a [`Task`], created with [`Task::builder`]
+pub struct TaskBuilder<'a, T, E> {
+    task: Option<TaskBody<'a, T, E>>,
+    dependencies: Dependency,
+    name: Option<String>,
+    group: Option<String>,
+    metadata: HashMap<String, String>,
+    priority: i32,
+    timeout: Option<Duration>,
+    exclusive: bool,
+    cost: Option<Duration>,
+    condition: Option<Condition<'a>>,
+}
+
+impl<T, E> Default for TaskBuilder<'_, T, E> {
+    fn default() -> Self {
+        Self {
+            task: None,
+            dependencies: Dependency::None,
+            name: None,
+            group: None,
+            metadata: HashMap::new(),
+            priority: 0,
+            timeout: None,
+            exclusive: false,
+            cost: None,
+            condition: None,
+        }
+    }
+}
+
+impl<'a, T, E> TaskBuilder<'a, T, E> {
+    /// Sets the future the task runs
+    pub fn future<F: Future<Output = Result<T, E>> + Send + 'a>(mut self, task: F) -> Self {
+        self.task = Some(TaskBody::Future(Box::pin(task)));
+        self
+    }
+
+    /// Adds dependencies to the task, on top of the ones already declared
+    pub fn depends_on(mut self, dependencies: impl Into<Dependency>) -> Self {
+        self.dependencies = match self.dependencies {
+            Dependency::None => dependencies.into(),
+            existing => existing.and(dependencies),
+        };
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    pub fn cost(mut self, cost: Duration) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Only runs the task if `predicate` returns `true`, see [`Task::when`]
+    pub fn when(mut self, predicate: impl Fn() -> bool + Send + Sync + 'a) -> Self {
+        self.condition = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Builds the task, failing if no future was provided
+    pub fn build(self) -> Result<Task<'a, T, E>, BuildError> {
+        let task = self.task.ok_or(BuildError::MissingFuture)?;
+
+        Ok(Task {
+            name: self.name,
+            group: self.group,
+            metadata: self.metadata,
+            priority: self.priority,
+            timeout: self.timeout,
+            exclusive: self.exclusive,
+            cost: self.cost,
+            condition: self.condition,
+            ..Task::from_body(task, self.dependencies)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future;
+
+    fn run_in_place() -> BlockingRunner<'static> {
+        Arc::new(|job| job())
+    }
+
+    #[test]
+    fn test_new_independent() {
+        let task = crate::Task::new_independent(future::ready(Ok::<(), ()>(())));
+
+        assert_eq!(*task.dependencies(), Dependency::None);
+        assert_eq!(task.dependencies().iter().count(), 0);
+    }
+
+    #[test]
+    fn test_new_with_vec_dependencies() {
+        let (dep1, dep2) = (TaskId::generate(), TaskId::generate());
+        let task = Task::new(future::ready(Ok::<(), ()>(())), vec![dep1, dep2]);
+
+        assert_eq!(
+            task.dependencies().iter().collect::<Vec<_>>(),
+            vec![dep1, dep2]
+        );
+    }
+
+    #[test]
+    fn test_new_with_structured_dependency() {
+        let (dep1, dep2) = (TaskId::generate(), TaskId::generate());
+        let dependency = Dependency::Combine(
+            Box::new(Dependency::Task(dep1)),
+            Box::new(Dependency::Task(dep2)),
+        );
+        let task = Task::new(future::ready(Ok::<(), ()>(())), dependency.clone());
+
+        assert_eq!(*task.dependencies(), dependency);
+        assert_eq!(
+            task.dependencies().iter().collect::<Vec<_>>(),
+            vec![dep1, dep2]
+        );
+    }
+
+    #[test]
+    fn test_weak_dependency_structure_preserved() {
+        let (dep1, dep2) = (TaskId::generate(), TaskId::generate());
+        let dependency = Dependency::Task(dep1).and(Dependency::Weak(dep2));
+        let task = Task::new(future::ready(Ok::<(), ()>(())), dependency.clone());
+
+        assert_eq!(*task.dependencies(), dependency);
+        assert_eq!(task.dependencies().iter().collect::<Vec<_>>(), vec![dep1]);
+        assert_eq!(
+            task.dependencies().iter_weak().collect::<Vec<_>>(),
+            vec![dep2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_id() {
+        let id = TaskId::from_name("load-config");
+        let task = Task::new_independent(future::ready(Ok::<i32, ()>(1))).with_id(id);
+        assert_eq!(*task.id(), id);
+
+        let dependent = Task::new(future::ready(Ok::<i32, ()>(2)), id);
+        let dependent_id = *dependent.id();
+        let builder = crate::TaskExecutorBuilder::new(crate::ExecutionMode::true_async());
+        builder.insert(dependent).insert(task);
+        let result = builder.build().unwrap().execute().await.unwrap();
+
+        assert_eq!(result.successful_tasks, 2);
+        assert_eq!(result.steps[0][0].task_id(), id);
+        assert_eq!(result.steps[1][0].task_id(), dependent_id);
+    }
+
+    #[tokio::test]
+    async fn test_from_boxed() {
+        let dependency = TaskId::generate();
+        let future: UnitTask<'static, i32, ()> = Box::pin(async { Ok(7) });
+        let task = Task::from_boxed(future, dependency);
+
+        assert_eq!(
+            task.dependencies().iter().collect::<Vec<_>>(),
+            vec![dependency]
+        );
+        let TaskBody::Future(future) = task.task else {
+            panic!("Expected a future body");
+        };
+        assert_eq!(future.await, Ok(7));
+    }
+
+    #[tokio::test]
+    async fn test_task_map() {
+        let dependency = TaskId::generate();
+        let task = Task::new(future::ready(Ok::<i32, ()>(21)), [dependency]).with_name("double");
+        let task_id = *task.id();
+
+        let mapped: Task<String, ()> = task.map(|value| (value * 2).to_string());
+
+        assert_eq!(*mapped.id(), task_id);
+        assert_eq!(
+            mapped.dependencies().iter().collect::<Vec<_>>(),
+            vec![dependency]
+        );
+        assert_eq!(mapped.name().as_deref(), Some("double"));
+        assert_eq!(
+            mapped
+                .into_task(&|_| None, run_in_place(), Default::default())
+                .await,
+            Ok("42".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_then() {
+        let dependency = TaskId::generate();
+        let task = Task::new(future::ready(Ok::<i32, String>(21)), [dependency]);
+        let task_id = *task.id();
+
+        let chained = task.then(|value| async move { Ok(format!("{}", value * 2)) });
+        assert_eq!(*chained.id(), task_id);
+        assert_eq!(
+            chained.dependencies().iter().collect::<Vec<_>>(),
+            vec![dependency]
+        );
+        assert_eq!(
+            chained
+                .into_task(&|_| None, run_in_place(), Default::default())
+                .await,
+            Ok("42".to_string())
+        );
+
+        let failing: Task<(), String> =
+            Task::new_independent(future::ready(Err::<i32, String>("boom".into())))
+                .then(|_| async { panic!("The continuation must not run after a failure") });
+        assert_eq!(
+            failing
+                .into_task(&|_| None, run_in_place(), Default::default())
+                .await,
+            Err("boom".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_map_with_inputs() {
+        let dependency = TaskId::generate();
+        let with_inputs = || {
+            Task::with_inputs(
+                |inputs: HashMap<TaskId, i32>| future::ready(Ok::<i32, ()>(inputs.len() as i32)),
+                dependency,
+            )
+        };
+
+        let task = with_inputs();
+        let task_id = *task.id();
+        assert!(matches!(
+            task.try_map(|value| value + 1),
+            Err(BuildError::OutputTypeFixed(id)) if id == task_id
+        ));
+        assert!(matches!(
+            with_inputs().try_then(|value| future::ready(Ok(value + 1))),
+            Err(BuildError::OutputTypeFixed(_))
+        ));
+        assert!(
+            Task::new_independent(future::ready(Ok::<i32, ()>(1)))
+                .try_map(|value| value + 1)
+                .is_ok()
+        );
+
+        // Once mapped, the outputs of its dependencies are no longer handed over
+        let mapped: Task<String, ()> = with_inputs().map(|count| count.to_string());
+        let output = "unrelated".to_string();
+        assert_eq!(
+            mapped
+                .into_task(&|_| Some(&output), run_in_place(), Default::default())
+                .await,
+            Ok("0".to_string())
+        );
+        let chained = with_inputs().then(|count| future::ready(Ok(count + 1)));
+        assert_eq!(
+            chained
+                .into_task(&|_| Some(&5), run_in_place(), Default::default())
+                .await,
+            Ok(1)
+        );
+        let boxed = with_inputs().into_any();
+        let output = boxed
+            .into_task(&|_| None, run_in_place(), Default::default())
+            .await
+            .unwrap();
+        assert_eq!(output.downcast_ref::<i32>(), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn test_task_map_err() {
+        let task = Task::new_independent(future::ready(Err::<i32, i32>(7)));
+        let mapped: Task<i32, String> = task.map_err(|err| format!("error {err}"));
+
+        assert_eq!(
+            mapped
+                .into_task(&|_| None, run_in_place(), Default::default())
+                .await,
+            Err("error 7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_task_builder() {
+        let (dep1, dep2) = (TaskId::generate(), TaskId::generate());
+        let task = Task::builder()
+            .future(future::ready(Ok::<i32, ()>(1)))
+            .depends_on(dep1)
+            .depends_on([dep2])
+            .name("fetch-users")
+            .metadata("team", "billing")
+            .priority(3)
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            task.dependencies().iter().collect::<Vec<_>>(),
+            vec![dep1, dep2]
+        );
+        assert_eq!(task.name().as_deref(), Some("fetch-users"));
+        assert_eq!(task.metadata()["team"], "billing");
+        assert_eq!(*task.priority(), 3);
+        assert_eq!(*task.timeout(), Some(Duration::from_secs(5)));
+        assert!(task.should_run());
+
+        let gated = Task::builder()
+            .future(future::ready(Ok::<i32, ()>(1)))
+            .when(|| false)
+            .build()
+            .unwrap();
+        assert!(!gated.should_run());
+
+        let missing = Task::<i32, ()>::builder().name("no future").build();
+        assert!(matches!(missing, Err(BuildError::MissingFuture)));
+    }
+
+    #[tokio::test]
+    async fn test_from_blocking() {
+        let task = Task::from_blocking(|| Ok::<i32, ()>(6 * 7), []).map(|value| value + 1);
+
+        assert_eq!(
+            task.into_task(&|_| None, run_in_place(), Default::default())
+                .await,
+            Ok(43)
+        );
+    }
+}